@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use crate::format::Format;
+use crate::instance::KnapsackInstance;
+use crate::load::{load_dataset, load_instance};
+use crate::resolution::{Knapsack, SolveResult, SolverParams, solve_knapsack, solve_all};
+#[cfg(feature = "sqlite")]
+use crate::sqlite::ResultsDb;
+
+#[derive(Debug, Args)]
+pub struct Bench {
+    /// The paths to the instance files
+    pub instances: Vec<String>,
+    /// The path to a json file holding an array of instances
+    #[clap(short, long)]
+    pub dataset: Option<String>,
+    /// The format of the instance files (guessed from their content when omitted)
+    #[clap(short, long, value_enum)]
+    pub format: Option<Format>,
+    /// max number of nodes in a layer
+    #[clap(short, long, default_value="100")]
+    pub width: usize,
+    /// timeout (in seconds) of each resolution
+    #[clap(short, long, default_value="60")]
+    pub timeout: u64,
+    /// solve the instances of the dataset concurrently (requires the rayon feature)
+    #[clap(long)]
+    pub parallel: bool,
+    /// how the results are printed
+    #[clap(long, value_enum, default_value="csv")]
+    pub output_format: OutputFormat,
+    /// only print aggregate statistics over all the instances, after solving them
+    #[clap(long)]
+    pub summary_only: bool,
+    /// append the results to the results table of this SQLite database
+    #[cfg(feature = "sqlite")]
+    #[clap(long)]
+    pub sqlite: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// one line of comma separated values per instance, after a header
+    Csv,
+    /// one json object per line per instance (json lines)
+    Jsonl,
+}
+
+/// The result of an instance as printed with --output-format jsonl
+#[derive(Debug, Serialize)]
+struct JsonLine<'a> {
+    instance: &'a str,
+    best_value: Option<isize>,
+    is_exact: bool,
+    upper_bound: isize,
+    explored: usize,
+    time_ms: u128,
+}
+
+/// The aggregate results printed with --summary-only
+#[derive(Debug, Default)]
+struct Summary {
+    durations: Vec<Duration>,
+    exact: usize,
+    explored: usize,
+}
+
+impl Summary {
+    fn add(&mut self, result: &SolveResult) {
+        self.durations.push(result.duration);
+        self.exact += result.is_exact as usize;
+        self.explored += result.explored;
+    }
+
+    /// The resolutions that did not prove optimality ran out of time (or nodes)
+    fn print(&mut self) {
+        let count = self.durations.len();
+        self.durations.sort_unstable();
+        let median = match count {
+            0 => Duration::ZERO,
+            _ if count % 2 == 1 => self.durations[count / 2],
+            _ => (self.durations[count / 2 - 1] + self.durations[count / 2]) / 2,
+        };
+        let mean = self.durations.iter().sum::<Duration>() / count.max(1) as u32;
+
+        println!("instances {count}");
+        println!("solved exactly {}", self.exact);
+        println!("timeouts {}", count - self.exact);
+        println!("median time {:.3}s", median.as_secs_f64());
+        println!("mean time {:.3}s", mean.as_secs_f64());
+        println!("total nodes {}", self.explored);
+    }
+}
+
+impl Bench {
+    pub fn bench(&self) {
+        if self.output_format == OutputFormat::Csv && !self.summary_only {
+            println!("instance,best_value,is_exact,time_ms");
+        }
+
+        // the results of each instance are stored in the database (if any) as soon
+        // as they are known, then printed or added to the summary
+        let mut summary = Summary::default();
+        #[cfg(feature = "sqlite")]
+        let db = self.sqlite.as_ref().map(|path| ResultsDb::open(path));
+        let mut record = |name: &str, result: &SolveResult| {
+            #[cfg(feature = "sqlite")]
+            if let Some(db) = db.as_ref() {
+                db.insert(name, result, self.width);
+            }
+            self.print(name, result, &mut summary);
+        };
+
+        if let Some(dataset) = self.dataset.as_ref() {
+            let results = solve_all(load_dataset(dataset).unwrap_or_else(|error| panic!("{error}")).into_iter(), &self.params(), self.parallel);
+            for (i, result) in results.into_iter().enumerate() {
+                match result {
+                    Ok(result) => record(&i.to_string(), &result),
+                    Err(error) => eprintln!("instance {i} skipped: {error}"),
+                }
+            }
+        }
+
+        for path in self.instances.iter() {
+            record(path, &self.run(load_instance(path, self.format).unwrap_or_else(|error| panic!("{error}"))));
+        }
+
+        if self.summary_only {
+            summary.print();
+        }
+    }
+
+    fn run(&self, instance: KnapsackInstance) -> SolveResult {
+        let problem = Knapsack::new(instance);
+        solve_knapsack(&problem, &self.params())
+    }
+
+    /// Prints the result of an instance, or only adds it to the summary with --summary-only
+    fn print(&self, name: &str, result: &SolveResult, summary: &mut Summary) {
+        if self.summary_only {
+            summary.add(result);
+            return;
+        }
+
+        match self.output_format {
+            OutputFormat::Csv => {
+                let best_value = result.best_value.map(|v| v.to_string()).unwrap_or_default();
+                println!("{name},{best_value},{},{}", result.is_exact, result.duration.as_millis());
+            },
+            OutputFormat::Jsonl => {
+                let line = JsonLine {
+                    instance: name,
+                    best_value: result.best_value,
+                    is_exact: result.is_exact,
+                    upper_bound: result.upper_bound,
+                    explored: result.explored,
+                    time_ms: result.duration.as_millis(),
+                };
+                println!("{}", serde_json::to_string(&line).unwrap());
+            },
+        }
+    }
+
+    fn params(&self) -> SolverParams {
+        SolverParams {
+            width: self.width,
+            timeout: Duration::from_secs(self.timeout),
+            ..Default::default()
+        }
+    }
+}
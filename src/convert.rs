@@ -0,0 +1,52 @@
+use std::fs::File;
+use std::io::Write;
+
+use clap::Args;
+
+use crate::format::Format;
+use crate::load::load_instance;
+
+#[derive(Debug, Args)]
+pub struct Convert {
+    /// The path to the instance file
+    #[clap(short, long)]
+    input: String,
+    /// The format of the instance file (guessed from its content when omitted)
+    #[clap(long, value_enum)]
+    from: Option<Format>,
+    /// The format in which the instance is written
+    #[clap(long, value_enum, default_value="json")]
+    to: Format,
+    /// Sort the items into a normal form, by increasing weight then profit
+    #[clap(long)]
+    canonicalize: bool,
+    /// Write the original index of each item of the canonical instance to this file, as a json array
+    #[clap(long, requires = "canonicalize")]
+    permutation_out: Option<String>,
+    /// Name of the file where to write the converted instance
+    #[clap(short, long)]
+    output: Option<String>,
+}
+
+impl Convert {
+    pub fn convert(&self) {
+        let mut instance = load_instance(&self.input, self.from).unwrap_or_else(|error| panic!("{error}"));
+        if self.canonicalize {
+            let (canonical, permutation) = instance.canonicalize();
+            // the permutation is kept out of the instance, which must not depend on
+            // the original order of its items
+            if let Some(path) = self.permutation_out.as_ref() {
+                File::create(path).unwrap().write_all(serde_json::to_string(&permutation).unwrap().as_bytes()).unwrap();
+            }
+            instance = canonical;
+        }
+
+        let converted = self.to.write(&instance);
+
+        if let Some(output) = self.output.as_ref() {
+            File::create(output).unwrap().write_all(converted.as_bytes()).unwrap();
+        } else {
+            println!("{}", converted.trim_end());
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! This module implements the `difficulty-estimate` subcommand, which ranks
+//! instances by cheap proxies of their hardness without compiling any decision
+//! diagram. Apart from sorting the items by ratio, every proxy is computed in a
+//! single pass over the items.
+
+use clap::Args;
+
+use crate::format::Format;
+use crate::instance::KnapsackInstance;
+use crate::load::{load_dataset, load_instance};
+use crate::resolution::Knapsack;
+
+#[derive(Debug, Args)]
+pub struct DifficultyEstimate {
+    /// The paths to the instance files
+    pub instances: Vec<String>,
+    /// The path to a json file holding an array of instances
+    #[clap(short, long)]
+    pub dataset: Option<String>,
+    /// The format of the instance files (guessed from their content when omitted)
+    #[clap(short, long, value_enum)]
+    pub format: Option<Format>,
+    /// Print the hardest instances first
+    #[clap(long)]
+    pub sort: bool,
+}
+
+/// The proxies of the difficulty of an instance
+#[derive(Debug, Clone)]
+pub struct Difficulty {
+    /// The ratio of the capacity to the total weight
+    pub tightness: f64,
+    /// The coefficient of variation of the profit/weight ratios
+    pub ratio_spread: f64,
+    /// The Pearson correlation coefficient between the weights and the profits
+    pub correlation: f64,
+    /// The number of items whose ratio ties with another item
+    pub ratio_ties: usize,
+    /// The composite score, between 0 (easy) and 1 (hard)
+    pub score: f64,
+}
+
+impl Difficulty {
+    pub fn of(instance: &KnapsackInstance) -> Self {
+        let tightness = instance.tightness();
+
+        let ratios = (0..instance.nb_items)
+            .filter(|i| instance.weight[*i] > 0)
+            .map(|i| instance.profit[i] as f64 / instance.weight[i] as f64)
+            .collect::<Vec<f64>>();
+        let (mean, deviation) = mean_and_deviation(&ratios);
+        let ratio_spread = if mean != 0.0 { deviation / mean.abs() } else { 0.0 };
+
+        let correlation = correlation(&instance.weight, &instance.profit);
+
+        let (ratio_ties, _) = Knapsack::new(instance.clone()).ratio_ties();
+
+        // each proxy is mapped to [0, 1], 1 being the hardest: the instances whose
+        // capacity is about half the total weight, whose ratios are close to each other,
+        // whose profits grow with the weights or whose items are interchangeable leave
+        // the bounds little room to prune
+        let proxies = [
+            1.0 - (2.0 * tightness.clamp(0.0, 1.0) - 1.0).abs(),
+            1.0 / (1.0 + ratio_spread),
+            correlation.max(0.0),
+            ratio_ties as f64 / instance.nb_items.max(1) as f64,
+        ];
+        let score = proxies.iter().sum::<f64>() / proxies.len() as f64;
+
+        Difficulty { tightness, ratio_spread, correlation, ratio_ties, score }
+    }
+}
+
+fn mean_and_deviation(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    (mean, variance.sqrt())
+}
+
+/// The Pearson correlation coefficient of the two series, 0 when one of them is constant
+pub fn correlation(x: &[isize], y: &[isize]) -> f64 {
+    let x = x.iter().map(|v| *v as f64).collect::<Vec<f64>>();
+    let y = y.iter().map(|v| *v as f64).collect::<Vec<f64>>();
+    let (mean_x, deviation_x) = mean_and_deviation(&x);
+    let (mean_y, deviation_y) = mean_and_deviation(&y);
+    if deviation_x == 0.0 || deviation_y == 0.0 {
+        return 0.0;
+    }
+    let covariance = x.iter().zip(y.iter()).map(|(a, b)| (a - mean_x) * (b - mean_y)).sum::<f64>() / x.len() as f64;
+    covariance / (deviation_x * deviation_y)
+}
+
+impl DifficultyEstimate {
+    pub fn estimate(&self) {
+        let mut estimates = vec![];
+        if let Some(dataset) = self.dataset.as_ref() {
+            let instances = load_dataset(dataset).unwrap_or_else(|error| panic!("{error}"));
+            for (i, instance) in instances.iter().enumerate() {
+                estimates.push((format!("{dataset}[{i}]"), Difficulty::of(instance)));
+            }
+        }
+        for path in self.instances.iter() {
+            let instance = load_instance(path, self.format).unwrap_or_else(|error| panic!("{error}"));
+            estimates.push((path.clone(), Difficulty::of(&instance)));
+        }
+
+        if self.sort {
+            estimates.sort_by(|a, b| b.1.score.total_cmp(&a.1.score));
+        }
+
+        println!("instance,tightness,ratio_spread,correlation,ratio_ties,score");
+        for (name, d) in estimates {
+            println!("{name},{:.4},{:.4},{:.4},{},{:.4}", d.tightness, d.ratio_spread, d.correlation, d.ratio_ties, d.score);
+        }
+    }
+}
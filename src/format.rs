@@ -0,0 +1,258 @@
+//! This module implements the text formats in which knapsack instances can be
+//! stored, and the detection of the format of a file from its content.
+//!
+//! * `json`: the serialization of `KnapsackInstance`.
+//! * `pisinger`: the format of Pisinger's generator. The first line holds the
+//!   number of items n, the n following lines hold the index, profit and weight
+//!   of an item separated by whitespace, and the last line holds the capacity.
+//! * `csv`: the first line holds the number of items and the capacity, each of
+//!   the following lines holds the weight and the profit of an item, separated
+//!   by a comma.
+//! * `dimacs`: a DIMACS-like format made of lines starting with a letter telling
+//!   their kind. Comment lines start with `c`, the problem line `p knapsack <n>
+//!   <capacity>` comes before the items, and each of the n item lines reads
+//!   `i <weight> <profit>`, in the order of the items.
+//! * `lp`: the CPLEX LP format of the 0/1 integer program (binary variable `x<i>`
+//!   for each item, a single `capacity` constraint and the total profit as
+//!   objective to maximize), to cross-check the results with a MILP solver.
+//! * `mps`: the same integer program in the (free) MPS format.
+//!
+//! Only `json` keeps the cluster, the metadata and the scale of the instance. The
+//! `lp` and `mps` formats can only be written.
+
+use clap::ValueEnum;
+
+use crate::instance::KnapsackInstance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Json,
+    Pisinger,
+    Csv,
+    Dimacs,
+    Lp,
+    Mps,
+}
+
+impl Format {
+    /// Guesses the format of the given content. A content starting with `{` is
+    /// json, a content whose first line holds a comma is csv, a content whose
+    /// first line is a bare integer is pisinger and a content whose first line is
+    /// a dimacs comment or problem line is dimacs.
+    pub fn sniff(content: &str) -> Option<Format> {
+        let first_line = content.lines().map(str::trim).find(|l| !l.is_empty())?;
+
+        if first_line.starts_with('{') {
+            Some(Format::Json)
+        } else if first_line.contains(',') {
+            Some(Format::Csv)
+        } else if first_line.parse::<usize>().is_ok() {
+            Some(Format::Pisinger)
+        } else if first_line.starts_with("c ") || first_line == "c" || first_line.starts_with("p ") {
+            Some(Format::Dimacs)
+        } else {
+            None
+        }
+    }
+
+    /// Writes the instance in this format. Only the json format retains the
+    /// clusters and the metadata of the instance.
+    pub fn write(&self, instance: &KnapsackInstance) -> String {
+        match self {
+            Format::Json => serde_json::to_string_pretty(instance).unwrap(),
+            Format::Pisinger => {
+                let mut out = format!("{}\n", instance.nb_items);
+                for i in 0..instance.nb_items {
+                    out.push_str(&format!("{} {} {}\n", i + 1, instance.profit[i], instance.weight[i]));
+                }
+                out.push_str(&format!("{}\n", instance.capacity));
+                out
+            },
+            Format::Csv => {
+                let mut out = format!("{},{}\n", instance.nb_items, instance.capacity);
+                for i in 0..instance.nb_items {
+                    out.push_str(&format!("{},{}\n", instance.weight[i], instance.profit[i]));
+                }
+                out
+            },
+            Format::Dimacs => {
+                let mut out = format!("p knapsack {} {}\n", instance.nb_items, instance.capacity);
+                for i in 0..instance.nb_items {
+                    out.push_str(&format!("i {} {}\n", instance.weight[i], instance.profit[i]));
+                }
+                out
+            },
+            Format::Lp => write_lp(instance),
+            Format::Mps => write_mps(instance),
+        }
+    }
+
+    /// Parses an instance stored in this format
+    pub fn parse(&self, content: &str) -> Result<KnapsackInstance, String> {
+        match self {
+            Format::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+            Format::Pisinger => parse_pisinger(content),
+            Format::Csv => parse_csv(content),
+            Format::Dimacs => parse_dimacs(content),
+            Format::Lp | Format::Mps => Err(format!("reading the {self:?} format is not supported")),
+        }
+    }
+}
+
+fn write_lp(instance: &KnapsackInstance) -> String {
+    let terms = |coefficients: &[isize]| {
+        let mut out = String::new();
+        for (i, c) in coefficients.iter().enumerate() {
+            let sign = if *c < 0 { " -" } else if i > 0 { " +" } else { "" };
+            out.push_str(&format!("{sign} {} x{i}", c.unsigned_abs()));
+        }
+        if out.is_empty() { " 0".to_string() } else { out }
+    };
+
+    let mut out = format!("\\ knapsack with {} items\n", instance.nb_items);
+    out.push_str(&format!("Maximize\n obj:{}\n", terms(&instance.profit)));
+    out.push_str(&format!("Subject To\n capacity:{} <= {}\n", terms(&instance.weight), instance.capacity));
+    out.push_str("Binary\n");
+    for i in 0..instance.nb_items {
+        out.push_str(&format!(" x{i}\n"));
+    }
+    out.push_str("End\n");
+    out
+}
+
+fn write_mps(instance: &KnapsackInstance) -> String {
+    let mut out = String::from("NAME knapsack\nOBJSENSE\n    MAX\nROWS\n N obj\n L capacity\nCOLUMNS\n");
+    out.push_str("    MARKER 'MARKER' 'INTORG'\n");
+    for i in 0..instance.nb_items {
+        out.push_str(&format!("    x{i} obj {} capacity {}\n", instance.profit[i], instance.weight[i]));
+    }
+    out.push_str("    MARKER 'MARKER' 'INTEND'\n");
+    out.push_str(&format!("RHS\n    rhs capacity {}\nBOUNDS\n", instance.capacity));
+    for i in 0..instance.nb_items {
+        out.push_str(&format!(" BV bnd x{i}\n"));
+    }
+    out.push_str("ENDATA\n");
+    out
+}
+
+fn parse_number<T: std::str::FromStr>(token: Option<&str>, what: &str) -> Result<T, String> {
+    let token = token.ok_or_else(|| format!("missing {what}"))?;
+    token.trim().parse().map_err(|_| format!("invalid {what}: {token}"))
+}
+
+fn parse_pisinger(content: &str) -> Result<KnapsackInstance, String> {
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+
+    let nb_items: usize = parse_number(lines.next(), "number of items")?;
+    let mut weight = Vec::with_capacity(nb_items);
+    let mut profit = Vec::with_capacity(nb_items);
+    for _ in 0..nb_items {
+        let mut tokens = lines.next().ok_or("missing item")?.split_whitespace().skip(1);
+        profit.push(parse_number(tokens.next(), "profit")?);
+        weight.push(parse_number(tokens.next(), "weight")?);
+    }
+    let capacity = parse_number(lines.next(), "capacity")?;
+
+    Ok(KnapsackInstance { nb_items, capacity, weight, profit, ..Default::default() })
+}
+
+fn parse_csv(content: &str) -> Result<KnapsackInstance, String> {
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+
+    let mut header = lines.next().ok_or("empty file")?.split(',');
+    let nb_items: usize = parse_number(header.next(), "number of items")?;
+    let capacity = parse_number(header.next(), "capacity")?;
+
+    let mut weight = Vec::with_capacity(nb_items);
+    let mut profit = Vec::with_capacity(nb_items);
+    for line in lines {
+        let mut tokens = line.split(',');
+        weight.push(parse_number(tokens.next(), "weight")?);
+        profit.push(parse_number(tokens.next(), "profit")?);
+    }
+
+    Ok(KnapsackInstance { nb_items, capacity, weight, profit, ..Default::default() })
+}
+
+fn parse_dimacs(content: &str) -> Result<KnapsackInstance, String> {
+    let mut header = None;
+    let mut weight = vec![];
+    let mut profit = vec![];
+
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("c") => continue,
+            Some("p") => {
+                if header.is_some() {
+                    return Err("duplicate problem line".to_string());
+                }
+                if tokens.next() != Some("knapsack") {
+                    return Err(format!("expected `p knapsack <n> <capacity>`, found {line}"));
+                }
+                let nb_items: usize = parse_number(tokens.next(), "number of items")?;
+                let capacity: isize = parse_number(tokens.next(), "capacity")?;
+                header = Some((nb_items, capacity));
+            },
+            Some("i") => {
+                if header.is_none() {
+                    return Err("item line before the problem line".to_string());
+                }
+                weight.push(parse_number(tokens.next(), "weight")?);
+                profit.push(parse_number(tokens.next(), "profit")?);
+            },
+            _ => return Err(format!("unexpected line: {line}")),
+        }
+    }
+
+    let (nb_items, capacity) = header.ok_or("missing problem line")?;
+    Ok(KnapsackInstance { nb_items, capacity, weight, profit, ..Default::default() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Format;
+    use crate::instance::KnapsackInstance;
+
+    fn instance() -> KnapsackInstance {
+        KnapsackInstance { nb_items: 3, capacity: 10, weight: vec![4, 5, 6], profit: vec![7, 8, 9], ..Default::default() }
+    }
+
+    #[test]
+    fn sniff_each_format() {
+        assert_eq!(Format::sniff(&Format::Json.write(&instance())), Some(Format::Json));
+        assert_eq!(Format::sniff(&Format::Csv.write(&instance())), Some(Format::Csv));
+        assert_eq!(Format::sniff(&Format::Pisinger.write(&instance())), Some(Format::Pisinger));
+        assert_eq!(Format::sniff("\n\n  3\n1 7 4\n2 8 5\n3 9 6\n10\n"), Some(Format::Pisinger));
+        assert_eq!(Format::sniff("knapsack\n"), None);
+        assert_eq!(Format::sniff("  \n"), None);
+    }
+
+    #[test]
+    fn text_formats_round_trip() {
+        for format in [Format::Pisinger, Format::Csv] {
+            let read = format.parse(&format.write(&instance())).unwrap();
+            assert_eq!((read.nb_items, read.capacity), (3, 10), "{format:?}");
+            assert_eq!((read.weight, read.profit), (instance().weight, instance().profit), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn dimacs_round_trip() {
+        let written = Format::Dimacs.write(&instance());
+        assert_eq!(Format::sniff(&written), Some(Format::Dimacs));
+        let read = Format::Dimacs.parse(&written).unwrap();
+        assert_eq!((read.nb_items, read.capacity), (3, 10));
+        assert_eq!((read.weight, read.profit), (instance().weight, instance().profit));
+
+        let commented = format!("c generated for a test\nc\n{written}");
+        assert_eq!(Format::sniff(&commented), Some(Format::Dimacs));
+        assert_eq!(Format::Dimacs.parse(&commented).unwrap().weight, instance().weight);
+    }
+
+    #[test]
+    fn dimacs_items_need_a_problem_line() {
+        assert!(Format::Dimacs.parse("i 4 7\np knapsack 1 10\n").is_err());
+        assert!(Format::Dimacs.parse("c no problem line\n").is_err());
+    }
+}
@@ -1,20 +1,31 @@
-use std::{time::{SystemTime, UNIX_EPOCH}, fs::File, io::Write};
+use std::{time::{SystemTime, UNIX_EPOCH, Duration}, fs::File, io::Write, collections::{BTreeMap, HashSet}, path::Path};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 
-use clap::Args;
-use rand::{Rng, SeedableRng};
+use clap::{Args, ValueEnum};
+use rand::{Rng, RngCore, SeedableRng};
 use rand_chacha::ChaChaRng;
+use rand_pcg::Pcg64;
+use rand_xoshiro::Xoshiro256PlusPlus;
 use rand_distr::{Uniform, Normal, Distribution};
 
 use crate::instance::KnapsackInstance;
+use crate::format::Format;
+use crate::load::load_instance;
+use ddo::{Problem, Relaxation};
+
+use crate::resolution::{Knapsack, KnapsackRelax, SolverParams, gcd, solve_knapsack};
 
 #[derive(Debug, Args)]
 pub struct KnapsackGenerator {
     /// An optional seed to kickstart the instance generation
     #[clap(short='s', long)]
     seed: Option<u128>,
-    /// The number of items that must be produced
-    #[clap(short='n', long, default_value="10")]
-    nb_items: usize,
+    /// A human readable name from which the seed is derived (ignored when a seed is given)
+    #[clap(long)]
+    seed_from_name: Option<String>,
+    /// The number of items that must be produced (10 by default, or the total of the size classes)
+    #[clap(short='n', long)]
+    nb_items: Option<usize>,
     /// The number of clusters of similar items
     #[clap(short='c', long, default_value="3")]
     nb_clusters: usize,
@@ -42,40 +53,629 @@ pub struct KnapsackGenerator {
     /// Name of the file where to generate the knapsack instance
     #[clap(short, long)]
     output: Option<String>,
+    /// Build a deterministic instance on which the greedy ratio heuristic performs poorly
+    #[clap(long)]
+    adversarial_greedy: bool,
+    /// Only report the cluster split and the estimated output size, without generating anything
+    #[clap(long)]
+    dry_run: bool,
+    /// Solve the generated instance and record its optimal value in the metadata
+    #[clap(long)]
+    solve_after: bool,
+    /// The time budget (in seconds) of the resolution performed with --solve-after
+    #[clap(long, default_value="60")]
+    solve_timeout: u64,
+    /// The width of the decision diagrams used with --solve-after
+    #[clap(long, default_value="100")]
+    solve_width: usize,
+    /// Resample the instance until the greedy solution is at least this percentage below the optimum
+    #[clap(long)]
+    target_gap: Option<f64>,
+    /// The max number of instances sampled to reach the target gap
+    #[clap(long, default_value="10")]
+    max_attempts: usize,
+    /// Resample the instance (up to max-attempts times) until greedy is not optimal
+    #[clap(long, conflicts_with_all = ["target_gap", "adversarial_greedy"])]
+    require_nontrivial: bool,
+    /// Read the generated instance back and check that it matches what was written
+    #[clap(long)]
+    round_trip_check: bool,
+    /// The number of instances to generate (their files are suffixed with their index).
+    /// On Ctrl-C, the instance being generated is finished and written, then the
+    /// generation stops with exit code 130 after reporting how many instances were written
+    #[clap(long, default_value="1")]
+    count: usize,
+    /// In batch mode, interpolate the capacity of the instances between lo and hi (given as lo,hi)
+    #[clap(long, value_delimiter = ',', num_args = 2, value_names = ["LO", "HI"])]
+    capacity_sweep: Option<Vec<isize>>,
+    /// The number of small items (lightest third of the weight range), instead of clusters
+    #[clap(long, conflicts_with = "nb_clusters")]
+    small: Option<usize>,
+    /// The number of medium items (middle third of the weight range), instead of clusters
+    #[clap(long, conflicts_with = "nb_clusters")]
+    medium: Option<usize>,
+    /// The number of large items (heaviest third of the weight range), instead of clusters
+    #[clap(long, conflicts_with = "nb_clusters")]
+    large: Option<usize>,
+    /// The pseudo random generator, seeded with the same bytes whichever is chosen
+    #[clap(long, value_enum, default_value="chacha")]
+    rng: RngKind,
+    /// How the profits of the items are derived from their weights
+    #[clap(long, value_enum, default_value="uncorrelated")]
+    correlation: Correlation,
+    /// Lower the profit of the items whose profit/weight ratio exceeds this cap down to the cap
+    #[clap(long)]
+    max_profit_density: Option<f64>,
+    /// Scale the profits of the items so that they sum up to this total
+    #[clap(long, value_parser = clap::value_parser!(i64).range(1..))]
+    target_total_profit: Option<i64>,
+    /// Shift each profit by at most this fraction of its value (and at least 1) so that no two items share the same profit/weight ratio
+    #[clap(long)]
+    profit_noise: Option<f64>,
+    /// Label the instance as trivial, easy or hard in its metadata, from the gap between greedy and the Dantzig bound
+    #[clap(long)]
+    classify: bool,
+}
+
+/// The relative gap between the greedy value and the Dantzig bound up to which an
+/// instance is labelled as easy by --classify
+const EASY_GAP: f64 = 0.01;
+
+/// The pseudo random generators available to generate instances
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RngKind {
+    /// ChaCha with 20 rounds
+    Chacha,
+    /// PCG XSL RR 128/64
+    Pcg,
+    /// xoshiro256++
+    Xoshiro,
+}
+
+/// The classic families of knapsack instances, which differ by the relation
+/// between the weight and the profit of the items. With R the max weight, the
+/// profits of the correlated families are computed from the generated weights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Correlation {
+    /// keep the profits drawn independently from the weights
+    Uncorrelated,
+    /// profit = weight + a uniform noise in [-R/10, R/10]
+    Weak,
+    /// profit = weight + R/10
+    Strong,
+    /// profit = R - weight + R/10, so the heaviest items are the least profitable
+    InverseStrong,
+    /// profit = weight
+    SubsetSum,
 }
 
 impl KnapsackGenerator {
 
     pub fn generate(&mut self) {
+        if let (Some(nb_items), Some(counts)) = (self.nb_items, self.class_counts()) {
+            let total = counts.iter().sum::<usize>();
+            if nb_items != total {
+                panic!("--nb-items {nb_items} disagrees with the {total} items of the size classes");
+            }
+        }
+
+        if self.dry_run {
+            self.report_dry_run();
+            return;
+        }
+
+        let mut rng = self.rng();
+
+        // a batch stops between two instances when interrupted, a single instance
+        // keeps the default behavior of Ctrl-C
+        let interrupted = Arc::new(AtomicBool::new(false));
+        if self.count > 1 {
+            let flag = interrupted.clone();
+            ctrlc::set_handler(move || flag.store(true, Ordering::Relaxed))
+                .expect("cannot install the Ctrl-C handler");
+        }
+
+        for index in 0..self.count {
+            if interrupted.load(Ordering::Relaxed) {
+                Self::exit_interrupted(index, self.count);
+            }
+
+            if let Some(capacity) = self.swept_capacity(index) {
+                self.capacity = capacity;
+            }
+
+            let mut instance = self.next_instance(&mut rng);
+
+            if let Some(sweep) = self.capacity_sweep.as_ref() {
+                instance.metadata.insert("capacity_sweep".to_string(), sweep.clone().into());
+                instance.metadata.insert("batch_index".to_string(), index.into());
+            }
+
+            self.write(&instance, self.output_path(index).as_deref());
+        }
+
+        if interrupted.load(Ordering::Relaxed) {
+            Self::exit_interrupted(self.count, self.count);
+        }
+    }
+
+    /// Generates a single instance, as the first one `generate` would write, but
+    /// returns it instead of writing it
+    pub fn sample(&mut self) -> KnapsackInstance {
+        let mut rng = self.rng();
+        self.next_instance(&mut rng)
+    }
+
+    /// Generates the next instance with the given generator, along with the optimum
+    /// and the class recorded in its metadata when requested
+    fn next_instance(&mut self, rng: &mut impl Rng) -> KnapsackInstance {
+        let mut instance = if self.adversarial_greedy {
+            self.adversarial_greedy_instance()
+        } else if let Some(target) = self.target_gap {
+            self.target_gap_instance(rng, target)
+        } else if self.require_nontrivial {
+            self.nontrivial_instance(rng)
+        } else {
+            self.random_instance(rng)
+        };
+
+        if self.solve_after {
+            self.record_optimum(&mut instance);
+        }
+
+        if self.classify {
+            Self::classify(&mut instance);
+        }
+
+        instance
+    }
+
+    /// Reports how many instances of the batch were written before the interruption
+    fn exit_interrupted(written: usize, count: usize) -> ! {
+        std::io::stdout().flush().unwrap();
+        eprintln!("interrupted: {written} of {count} instances written");
+        std::process::exit(130);
+    }
+
+    fn write(&self, instance: &KnapsackInstance, output: Option<&str>) {
+        let serialized = serde_json::to_string_pretty(instance).unwrap();
+
+        if let Some(output) = output {
+            // the instance is written next to its destination then renamed, which is
+            // atomic on a same filesystem, so that an interrupted run never leaves a
+            // truncated instance behind
+            let partial = format!("{output}.partial");
+            let mut file = File::create(&partial).unwrap();
+            file.write_all(serialized.as_bytes()).unwrap();
+            file.sync_all().unwrap();
+            std::fs::rename(&partial, output).unwrap();
+        } else {
+            println!("{serialized}");
+        }
+
+        if self.round_trip_check {
+            let read_back = match output {
+                Some(output) => load_instance(output, Some(Format::Json)).unwrap_or_else(|error| panic!("{error}")),
+                None => serde_json::from_str(&serialized).unwrap(),
+            };
+            Self::check_round_trip(instance, &read_back);
+        }
+    }
+
+    /// The file where the instance with the given index of the batch is written.
+    /// When several instances are generated, `name.json` becomes `name_<index>.json`.
+    fn output_path(&self, index: usize) -> Option<String> {
+        let output = self.output.as_ref()?;
+        if self.count == 1 {
+            return Some(output.clone());
+        }
+        Some(indexed_path(output, index))
+    }
+
+    /// The capacity of the instance with the given index when the capacity sweeps
+    /// linearly from lo (first instance) to hi (last instance) across the batch
+    fn swept_capacity(&self, index: usize) -> Option<isize> {
+        let sweep = self.capacity_sweep.as_ref()?;
+        let (lo, hi) = (sweep[0], sweep[1]);
+        if self.count <= 1 {
+            return Some(lo);
+        }
+        Some(lo + (hi - lo) * index as isize / (self.count - 1) as isize)
+    }
+
+    /// Fails loudly when the instance read back differs from the generated one
+    fn check_round_trip(written: &KnapsackInstance, read: &KnapsackInstance) {
+        let mut mismatches = vec![];
+        if written.nb_items != read.nb_items { mismatches.push("nb_items"); }
+        if written.capacity != read.capacity { mismatches.push("capacity"); }
+        if written.weight != read.weight { mismatches.push("weight"); }
+        if written.profit != read.profit { mismatches.push("profit"); }
+        if written.cluster != read.cluster { mismatches.push("cluster"); }
+        if written.metadata != read.metadata { mismatches.push("metadata"); }
+        if written.scale != read.scale { mismatches.push("scale"); }
+        if written.capacities != read.capacities { mismatches.push("capacities"); }
+        if written.profit_scenarios != read.profit_scenarios { mismatches.push("profit_scenarios"); }
+
+        if !mismatches.is_empty() {
+            panic!("round trip check failed, mismatching fields: {}", mismatches.join(", "));
+        }
+    }
+
+    fn random_instance(&mut self, rng: &mut impl Rng) -> KnapsackInstance {
+        let mut instance = if let Some(counts) = self.class_counts() {
+            self.size_class_instance(rng, counts)
+        } else {
+            self.clustered_instance(rng)
+        };
+        self.correlate(rng, &mut instance);
+        if let Some(cap) = self.max_profit_density {
+            Self::cap_profit_density(&mut instance, cap);
+        }
+        if let Some(total) = self.target_total_profit {
+            Self::scale_total_profit(&mut instance, total as isize);
+        }
+        if let Some(eps) = self.profit_noise {
+            Self::add_profit_noise(rng, &mut instance, eps);
+        }
+        instance
+    }
+
+    /// Shifts the profit of the items whose ratio ties with the one of a previous
+    /// item by a random offset of at most `eps` times the profit (and at least 1),
+    /// until the tie is broken. This is applied last, so the profits can end up
+    /// slightly off the target total or the density cap. The items whose tie
+    /// persists after a few attempts are left untouched, and their number is
+    /// recorded in the metadata along with `eps`.
+    fn add_profit_noise(rng: &mut impl Rng, instance: &mut KnapsackInstance, eps: f64) {
+        assert!(eps >= 0.0 && eps.is_finite(), "the profit noise must be non-negative, found {eps}");
+        const ATTEMPTS: usize = 100;
+
+        // each ratio is stored as an irreducible fraction so that the ties are exact
+        let ratio = |profit: isize, weight: isize| {
+            let g = gcd(profit.unsigned_abs(), weight.unsigned_abs()).max(1) as isize;
+            (profit / g, weight / g)
+        };
+
+        let mut ratios = HashSet::new();
+        let mut unresolved = 0;
+        for i in 0..instance.nb_items {
+            let weight = instance.weight[i];
+            if weight == 0 {
+                continue;
+            }
+            let sampled = instance.profit[i];
+            let spread = ((eps * sampled.abs() as f64).round() as isize).max(1);
+            let offset = Uniform::new_inclusive(-spread, spread);
+
+            let mut attempts = 0;
+            while ratios.contains(&ratio(instance.profit[i], weight)) && attempts < ATTEMPTS {
+                instance.profit[i] = sampled + offset.sample(rng);
+                attempts += 1;
+            }
+            if !ratios.insert(ratio(instance.profit[i], weight)) {
+                instance.profit[i] = sampled;
+                unresolved += 1;
+            }
+        }
+
+        instance.metadata.insert("profit_noise".to_string(), eps.into());
+        instance.metadata.insert("profit_noise_unresolved".to_string(), unresolved.into());
+    }
+
+    /// Multiplies the profits by a single factor so that they sum up to the given
+    /// total. The rounding error is absorbed by the most profitable item, and the
+    /// factor is recorded in the metadata.
+    fn scale_total_profit(instance: &mut KnapsackInstance, total: isize) {
+        let current = instance.profit.iter().sum::<isize>();
+        assert!(current > 0, "cannot scale profits summing up to {current}");
+
+        let factor = total as f64 / current as f64;
+        instance.profit.iter_mut().for_each(|p| *p = (*p as f64 * factor).round() as isize);
+        let error = total - instance.profit.iter().sum::<isize>();
+        if let Some(max) = instance.profit.iter_mut().max() {
+            *max += error;
+        }
+
+        instance.metadata.insert("target_total_profit".to_string(), total.into());
+        instance.metadata.insert("profit_scaling".to_string(), factor.into());
+    }
+
+    /// Rescales the profit of the items whose ratio exceeds the cap to the largest
+    /// profit complying with it, and records the cap and the number of adjusted
+    /// items in the metadata.
+    fn cap_profit_density(instance: &mut KnapsackInstance, cap: f64) {
+        let mut adjusted = 0;
+        for i in 0..instance.nb_items {
+            let max_profit = (cap * instance.weight[i] as f64).floor() as isize;
+            if instance.profit[i] > max_profit {
+                instance.profit[i] = max_profit;
+                adjusted += 1;
+            }
+        }
+
+        instance.metadata.insert("max_profit_density".to_string(), cap.into());
+        instance.metadata.insert("density_adjusted".to_string(), adjusted.into());
+    }
+
+    /// Replaces the profits of the items by the ones of the requested correlation
+    /// family. The profits are clamped to 1 so that every item stays worth taking.
+    fn correlate(&self, rng: &mut impl Rng, instance: &mut KnapsackInstance) {
+        if self.correlation == Correlation::Uncorrelated {
+            return;
+        }
+
+        let range = self.max_weight as isize;
+        let k = (range / 10).max(1);
+        let noise = Uniform::new_inclusive(-k, k);
+
+        for i in 0..instance.nb_items {
+            let weight = instance.weight[i];
+            let profit = match self.correlation {
+                Correlation::Uncorrelated => instance.profit[i],
+                Correlation::Weak => weight + noise.sample(rng),
+                Correlation::Strong => weight + k,
+                Correlation::InverseStrong => range - weight + k,
+                Correlation::SubsetSum => weight,
+            };
+            instance.profit[i] = profit.max(1);
+        }
+
+        let mode = self.correlation.to_possible_value().unwrap().get_name().to_string();
+        instance.metadata.insert("correlation".to_string(), mode.into());
+    }
+
+    /// Generates the requested number of items in each size class. The weight and
+    /// profit ranges are both split in three equal parts: small items get their
+    /// weight and profit from the lower third of the ranges, medium ones from the
+    /// middle third and large ones from the upper third. The number of items is the
+    /// total of the class counts, and the class of each item is used as its cluster.
+    fn size_class_instance(&self, rng: &mut impl Rng, counts: [usize; 3]) -> KnapsackInstance {
+        let third = |min: usize, max: usize, class: usize| {
+            let span = (max - min) / 3;
+            let lo = min + class * span;
+            let hi = if class == 2 { max } else { lo + span };
+            Uniform::new_inclusive(lo, hi)
+        };
+
+        let mut weight = vec![];
+        let mut profit = vec![];
+        let mut cluster = vec![];
+        for (class, count) in counts.iter().enumerate() {
+            let rand_weight = third(self.min_weight, self.max_weight, class);
+            let rand_profit = third(self.min_profit, self.max_profit, class);
+            for _ in 0..*count {
+                weight.push(rand_weight.sample(rng) as isize);
+                profit.push(rand_profit.sample(rng) as isize);
+                cluster.push(class);
+            }
+        }
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert("size_classes".to_string(), counts.to_vec().into());
+
+        KnapsackInstance {
+            nb_items: self.nb_items(),
+            capacity: self.capacity,
+            weight,
+            profit,
+            cluster: Some(cluster),
+            metadata,
+            ..Default::default()
+        }
+    }
+
+    fn clustered_instance(&mut self, rng: &mut impl Rng) -> KnapsackInstance {
         if self.min_weight < self.weight_std_dev {
             self.max_weight += self.weight_std_dev - self.min_weight;
             self.min_weight = self.weight_std_dev;
         }
 
-        let mut rng = self.rng();
+        let nb_items_per_cluster = self.nb_items_per_cluster();
 
-        let mut nb_items_per_cluster = vec![self.nb_items / self.nb_clusters; self.nb_clusters];
-        for i in 0..(self.nb_items % self.nb_clusters) {
-            nb_items_per_cluster[i] += 1;
-        }
-        
-        let weight = Self::generate_vec(&mut rng, self.nb_clusters, &nb_items_per_cluster, self.min_weight, self.max_weight, self.weight_std_dev);
-        let profit = Self::generate_vec(&mut rng, self.nb_clusters, &nb_items_per_cluster, self.min_profit, self.max_profit, self.profit_std_dev);
+        let weight = Self::generate_vec(rng, self.nb_clusters, &nb_items_per_cluster, self.min_weight, self.max_weight, self.weight_std_dev);
+        let profit = Self::generate_vec(rng, self.nb_clusters, &nb_items_per_cluster, self.min_profit, self.max_profit, self.profit_std_dev);
+
+        let cluster = nb_items_per_cluster.iter().enumerate()
+            .flat_map(|(i, n)| std::iter::repeat(i).take(*n))
+            .collect();
 
-        let instance = KnapsackInstance {
-            nb_items: self.nb_items,
+        KnapsackInstance {
+            nb_items: self.nb_items(),
             capacity: self.capacity,
             weight,
             profit,
-        };
+            cluster: Some(cluster),
+            ..Default::default()
+        }
+    }
 
-        let instance = serde_json::to_string_pretty(&instance).unwrap();
+    /// The number of items of each size class, when any of them is given
+    fn class_counts(&self) -> Option<[usize; 3]> {
+        if self.small.is_none() && self.medium.is_none() && self.large.is_none() {
+            return None;
+        }
+        Some([self.small.unwrap_or(0), self.medium.unwrap_or(0), self.large.unwrap_or(0)])
+    }
 
-        if let Some(output) = self.output.as_ref() {
-            File::create(output).unwrap().write_all(instance.as_bytes()).unwrap();
+    /// The number of items of the instances
+    fn nb_items(&self) -> usize {
+        match self.class_counts() {
+            Some(counts) => counts.iter().sum(),
+            None => self.nb_items.unwrap_or(10),
+        }
+    }
+
+    fn nb_items_per_cluster(&self) -> Vec<usize> {
+        let mut nb_items_per_cluster = vec![self.nb_items() / self.nb_clusters; self.nb_clusters];
+        for i in 0..(self.nb_items() % self.nb_clusters) {
+            nb_items_per_cluster[i] += 1;
+        }
+        nb_items_per_cluster
+    }
+
+    /// Prints the number of items in each cluster and an estimate of the size of the
+    /// pretty-printed json file
+    fn report_dry_run(&self) {
+        match self.class_counts() {
+            Some([small, medium, large]) => println!("items per size class small {small}, medium {medium}, large {large}"),
+            None => println!("items per cluster {:?}", self.nb_items_per_cluster()),
+        }
+        println!("estimated size {} bytes", self.estimated_size());
+    }
+
+    /// Estimates the size of the pretty-printed json file. Each weight, profit and
+    /// cluster takes one line made of an indentation of 4 spaces, at most as many
+    /// digits as the max value, a comma and a newline.
+    fn estimated_size(&self) -> usize {
+        let digits = |v: usize| v.to_string().len();
+        let nb_clusters = if self.class_counts().is_some() { 3 } else { self.nb_clusters };
+        let header = 100 + digits(self.nb_items()) + digits(self.capacity.unsigned_abs());
+        let per_item = 18 + digits(self.max_weight + self.weight_std_dev) + digits(self.max_profit + self.profit_std_dev) + digits(nb_clusters);
+        header + self.nb_items() * per_item
+    }
+
+    /// Solves the instance and stores the best value found in its metadata. When
+    /// the time budget is exhausted, `is_exact` is false and the value is only a
+    /// lower bound on the optimum.
+    fn record_optimum(&self, instance: &mut KnapsackInstance) -> Option<isize> {
+        let problem = Knapsack::new(instance.clone());
+        let result = solve_knapsack(&problem, &SolverParams {
+            width: self.solve_width,
+            timeout: Duration::from_secs(self.solve_timeout),
+            ..Default::default()
+        });
+
+        instance.metadata.insert("optimal_value".to_string(), result.best_value.into());
+        instance.metadata.insert("is_exact".to_string(), result.is_exact.into());
+        result.best_value
+    }
+
+    /// Labels the instance from the relative gap between the greedy value and the
+    /// Dantzig bound (the optimum of the LP relaxation), without solving it:
+    /// - `trivial` when both are equal, which proves that greedy is optimal;
+    /// - `easy` when the gap is at most 1%;
+    /// - `hard` otherwise.
+    fn classify(instance: &mut KnapsackInstance) {
+        if instance.capacity < 0 {
+            return;
+        }
+
+        let problem = Knapsack::new(instance.clone());
+        let (greedy_value, _) = problem.greedy();
+        let bound = KnapsackRelax::new(problem.clone()).fast_upper_bound(&problem.initial_state());
+        let gap = if bound > 0 {
+            (bound - greedy_value) as f64 / bound as f64
+        } else {
+            0.0
+        };
+
+        let label = if greedy_value >= bound {
+            "trivial"
+        } else if gap <= EASY_GAP {
+            "easy"
         } else {
-            println!("{instance}");
+            "hard"
+        };
+        instance.metadata.insert("difficulty".to_string(), label.into());
+    }
+
+    /// Samples instances until the relative gap between the greedy solution and
+    /// the optimum reaches the given percentage, or the attempt budget is exhausted.
+    /// In the latter case, the instance with the largest gap is returned.
+    fn target_gap_instance(&mut self, rng: &mut impl Rng, target: f64) -> KnapsackInstance {
+        let mut best: Option<(f64, KnapsackInstance)> = None;
+
+        for _ in 0..self.max_attempts.max(1) {
+            let mut instance = self.random_instance(rng);
+            let gap = self.record_greedy_gap(&mut instance);
+
+            if best.as_ref().map(|(g, _)| gap > *g).unwrap_or(true) {
+                best = Some((gap, instance));
+            }
+            if 100.0 * gap >= target {
+                break;
+            }
+        }
+
+        let (gap, instance) = best.unwrap();
+        if 100.0 * gap < target {
+            eprintln!("warning: target gap of {target}% not reached after {} attempts, best gap is {:.2}%", self.max_attempts, 100.0 * gap);
+        }
+        instance
+    }
+
+    /// Samples instances until the best solution found by the solver beats the greedy
+    /// one, or the attempt budget is exhausted. The number of sampled instances is
+    /// recorded in the metadata.
+    fn nontrivial_instance(&mut self, rng: &mut impl Rng) -> KnapsackInstance {
+        let max_attempts = self.max_attempts.max(1);
+        for attempt in 1..=max_attempts {
+            let mut instance = self.random_instance(rng);
+            let gap = self.record_greedy_gap(&mut instance);
+
+            if gap > 0.0 || attempt == max_attempts {
+                if gap <= 0.0 {
+                    eprintln!("warning: greedy is still optimal after {max_attempts} attempts");
+                }
+                instance.metadata.insert("attempts".to_string(), attempt.into());
+                return instance;
+            }
         }
+        unreachable!()
+    }
+
+    /// Computes the relative gap between the greedy solution and the optimum of the
+    /// instance and records it in its metadata.
+    fn record_greedy_gap(&self, instance: &mut KnapsackInstance) -> f64 {
+        let (greedy_value, _) = Knapsack::new(instance.clone()).greedy();
+        let optimal_value = self.record_optimum(instance).unwrap_or(greedy_value);
+        let gap = if optimal_value > 0 {
+            (optimal_value - greedy_value) as f64 / optimal_value as f64
+        } else {
+            0.0
+        };
+
+        instance.metadata.insert("greedy_value".to_string(), greedy_value.into());
+        instance.metadata.insert("greedy_gap".to_string(), gap.into());
+        gap
+    }
+
+    /// Builds an instance where the item with the best ratio is a heavy item that
+    /// takes slightly more than half of the capacity. Once the greedy heuristic has
+    /// selected it, none of the light items fit anymore whereas two of them together
+    /// are worth almost twice as much.
+    fn adversarial_greedy_instance(&self) -> KnapsackInstance {
+        assert!(self.nb_items() >= 3, "an adversarial instance needs at least 3 items");
+
+        let light_weight = self.max_weight as isize;
+        let light_profit = self.max_profit as isize;
+        let heavy_weight = light_weight + 1;
+        let heavy_profit = light_profit * heavy_weight / light_weight + 1;
+        let capacity = 2 * light_weight;
+
+        let mut weight = vec![heavy_weight];
+        let mut profit = vec![heavy_profit];
+        weight.resize(self.nb_items(), light_weight);
+        profit.resize(self.nb_items(), light_profit);
+
+        let mut instance = KnapsackInstance {
+            nb_items: self.nb_items(),
+            capacity,
+            weight,
+            profit,
+            ..Default::default()
+        };
+
+        let (greedy_value, _) = Knapsack::new(instance.clone()).greedy();
+        let optimal_value = heavy_profit.max(2 * light_profit);
+        let gap = (optimal_value - greedy_value) as f64 / optimal_value as f64;
+
+        instance.metadata.insert("generator".to_string(), "adversarial-greedy".into());
+        instance.metadata.insert("greedy_value".to_string(), greedy_value.into());
+        instance.metadata.insert("optimal_value".to_string(), optimal_value.into());
+        instance.metadata.insert("greedy_gap".to_string(), gap.into());
+
+        instance
     }
 
     fn generate_vec(rng: &mut impl Rng, nb_clusters: usize, nb_items_per_cluster: &Vec<usize>, min_val: usize, max_val: usize, std_dev: usize) -> Vec<isize> {
@@ -94,12 +694,78 @@ impl KnapsackGenerator {
         data
     }
     
-    fn rng(&self) -> impl Rng {
+    fn rng(&self) -> Box<dyn RngCore> {
+        let seed = self.seed_bytes();
+        match self.rng {
+            RngKind::Chacha => Box::new(ChaChaRng::from_seed(seed)),
+            RngKind::Pcg => Box::new(Pcg64::from_seed(seed)),
+            RngKind::Xoshiro => Box::new(Xoshiro256PlusPlus::from_seed(seed)),
+        }
+    }
+
+    /// The 32 bytes from which the generator is seeded
+    fn seed_bytes(&self) -> [u8; 32] {
+        if let (None, Some(name)) = (self.seed, self.seed_from_name.as_ref()) {
+            return Self::seed_from_name(name);
+        }
+
         let init = self.seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis());
         let mut seed = [0_u8; 32];
         seed.iter_mut().zip(init.to_be_bytes().into_iter()).for_each(|(s, i)| *s = i);
         seed.iter_mut().rev().zip(init.to_le_bytes().into_iter()).for_each(|(s, i)| *s = i);
-        ChaChaRng::from_seed(seed)
+        seed
+    }
+
+    /// Hashes the given name into a 32 bytes seed. Each 8 bytes chunk is obtained
+    /// with an FNV-1a hash of the name salted with the index of the chunk, so that
+    /// the seed only depends on the name (and not on the platform or rust version).
+    fn seed_from_name(name: &str) -> [u8; 32] {
+        let mut seed = [0_u8; 32];
+        for (i, chunk) in seed.chunks_mut(8).enumerate() {
+            let mut hash = 0xcbf29ce484222325_u64;
+            for byte in std::iter::once(i as u8).chain(name.bytes()) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+            chunk.copy_from_slice(&hash.to_le_bytes());
+        }
+        seed
     }
 
-}
\ No newline at end of file
+}
+
+/// Inserts the given index before the extension of the given path: `name.json`
+/// becomes `name_<index>.json`
+pub fn indexed_path(output: &str, index: usize) -> String {
+    let path = Path::new(output);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("instance");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => format!("{stem}_{index}.{extension}"),
+        None => format!("{stem}_{index}"),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::KnapsackGenerator;
+
+    #[derive(Debug, Parser)]
+    struct Cli {
+        #[command(flatten)]
+        generator: KnapsackGenerator,
+    }
+
+    #[test]
+    fn dry_run_estimates_the_file_size() {
+        for args in [&["--nb-items", "30"][..], &["--nb-items", "500", "--nb-clusters", "12"]] {
+            let mut generator = Cli::parse_from(["generate", "--seed", "42"].iter().chain(args)).generator;
+            let estimate = generator.estimated_size();
+            let actual = serde_json::to_string_pretty(&generator.sample()).unwrap().len();
+            assert!(estimate >= actual, "{args:?}: estimated {estimate} bytes, wrote {actual}");
+            assert!(estimate <= actual + actual / 5, "{args:?}: estimated {estimate} bytes, wrote {actual}");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use clap::Args;
+use serde_json::json;
+
+#[derive(Debug, Args)]
+pub struct Info {
+    /// Print the capabilities as json
+    #[clap(long)]
+    json: bool,
+}
+
+impl Info {
+
+    pub fn info(&self) {
+        let features = Self::enabled_features();
+
+        if self.json {
+            let info = json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "ddo": "git+https://github.com/xgillard/ddo",
+                "solvers": ["ParBarrierSolverFc", "dp"],
+                "formats": ["json", "pisinger", "csv", "dimacs", "lp", "mps"],
+                "features": features,
+                "defaults": {
+                    "width": 100,
+                    "timeout": 60,
+                },
+            });
+            println!("{}", serde_json::to_string_pretty(&info).unwrap());
+        } else {
+            println!("version {}", env!("CARGO_PKG_VERSION"));
+            println!("ddo git+https://github.com/xgillard/ddo");
+            println!("solvers ParBarrierSolverFc dp");
+            println!("formats json pisinger csv dimacs lp mps");
+            println!("features {}", features.join(" "));
+            println!("default width 100");
+            println!("default timeout 60");
+        }
+    }
+
+    fn enabled_features() -> Vec<&'static str> {
+        let mut features = vec![];
+        if cfg!(feature = "net") {
+            features.push("net");
+        }
+        if cfg!(feature = "metrics") {
+            features.push("metrics");
+        }
+        if cfg!(feature = "rayon") {
+            features.push("rayon");
+        }
+        if cfg!(feature = "test-instances") {
+            features.push("test-instances");
+        }
+        if cfg!(feature = "profiling") {
+            features.push("profiling");
+        }
+        if cfg!(feature = "sqlite") {
+            features.push("sqlite");
+        }
+        features
+    }
+
+}
@@ -1,11 +1,168 @@
 //! This module defines an abstract representation of a knapsack instance.
 
+use std::{collections::BTreeMap, fmt};
+
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct KnapsackInstance {
     pub nb_items: usize,
     pub capacity: isize,
     pub weight: Vec<isize>,
     pub profit: Vec<isize>,
+    /// The cluster (category) each item belongs to, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<Vec<usize>>,
+    /// Free-form information describing how the instance was obtained
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+    /// The factor by which the original (possibly fractional) capacity and weights
+    /// were multiplied to obtain the integer `capacity` and `weight`. The solver only
+    /// ever works with the integers, the factor is only used to display the weights
+    /// in their original unit. The original values are recovered exactly when they
+    /// are multiples of `1 / scale`; otherwise they were rounded when the instance
+    /// was built, and the solution is only optimal for the rounded weights.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    /// The capacities of the knapsacks of a multiple knapsack instance, in which
+    /// case `capacity` is ignored by the multiple knapsack model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacities: Option<Vec<isize>>,
+    /// Alternative profit vectors for the same items, e.g. the outcomes of a
+    /// stochastic planning problem. The solver uses `profit` unless told to solve
+    /// each scenario.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profit_scenarios: Option<Vec<Vec<isize>>>,
+}
+
+impl KnapsackInstance {
+    /// Checks that the instance is well formed: there must be exactly one weight
+    /// and one profit (and cluster if any) per item, and the weights must be
+    /// non-negative.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.weight.len() != self.nb_items {
+            return Err(format!("found {} weights for {} items", self.weight.len(), self.nb_items));
+        }
+        if self.profit.len() != self.nb_items {
+            return Err(format!("found {} profits for {} items", self.profit.len(), self.nb_items));
+        }
+        if let Some(cluster) = self.cluster.as_ref() {
+            if cluster.len() != self.nb_items {
+                return Err(format!("found {} cluster memberships for {} items", cluster.len(), self.nb_items));
+            }
+        }
+        if let Some(item) = self.weight.iter().position(|w| *w < 0) {
+            return Err(format!("item {item} has a negative weight"));
+        }
+        if let Some(capacities) = self.capacities.as_ref() {
+            if capacities.is_empty() {
+                return Err("the list of capacities is empty".to_string());
+            }
+            if let Some(bin) = capacities.iter().position(|c| *c < 0) {
+                return Err(format!("knapsack {bin} has a negative capacity"));
+            }
+        }
+        if let Some(scenarios) = self.profit_scenarios.as_ref() {
+            if let Some(s) = scenarios.iter().position(|p| p.len() != self.nb_items) {
+                return Err(format!("found {} profits in scenario {s} for {} items", scenarios[s].len(), self.nb_items));
+            }
+        }
+        if let Some(scale) = self.scale {
+            if !(scale.is_finite() && scale > 0.0) {
+                return Err(format!("the scale must be positive, found {scale}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// The total weight of the items
+    pub fn total_weight(&self) -> isize {
+        self.weight.iter().sum()
+    }
+
+    /// The number of clusters, i.e. one more than the largest cluster of an item (0
+    /// when the instance does not tell the cluster of its items)
+    pub fn nb_clusters(&self) -> usize {
+        self.cluster.as_ref().and_then(|c| c.iter().max()).map_or(0, |c| c + 1)
+    }
+
+    /// The ratio of the capacity to the total weight of the items
+    pub fn tightness(&self) -> f64 {
+        let total_weight = self.total_weight();
+        if total_weight > 0 { self.capacity as f64 / total_weight as f64 } else { 0.0 }
+    }
+
+    /// Groups the items with the same weight and profit. Returns the number of
+    /// groups of at least two items and the size of the largest group.
+    pub fn duplicate_groups(&self) -> (usize, usize) {
+        let mut groups = BTreeMap::<(isize, isize), usize>::new();
+        for i in 0..self.nb_items {
+            *groups.entry((self.weight[i], self.profit[i])).or_default() += 1;
+        }
+        let duplicates = groups.values().filter(|size| **size > 1).count();
+        let largest = groups.values().copied().max().unwrap_or(0);
+        (duplicates, largest)
+    }
+
+    /// Converts an integer weight (or capacity) back to the original unit
+    pub fn unscale(&self, weight: isize) -> f64 {
+        weight as f64 / self.scale.unwrap_or(1.0)
+    }
+
+    /// Reorders the items by increasing (weight, profit, cluster) so that two
+    /// instances differing only by the order of their items have the same normal
+    /// form. Returns the canonical instance along with the permutation applied:
+    /// item `i` of the canonical instance is item `permutation[i]` of this one.
+    pub fn canonicalize(&self) -> (KnapsackInstance, Vec<usize>) {
+        let cluster = |i: usize| self.cluster.as_ref().map(|c| c[i]);
+
+        let mut permutation = (0..self.nb_items).collect::<Vec<usize>>();
+        permutation.sort_by_key(|i| (self.weight[*i], self.profit[*i], cluster(*i)));
+
+        let canonical = KnapsackInstance {
+            nb_items: self.nb_items,
+            capacity: self.capacity,
+            weight: permutation.iter().map(|i| self.weight[*i]).collect(),
+            profit: permutation.iter().map(|i| self.profit[*i]).collect(),
+            cluster: self.cluster.as_ref().map(|c| permutation.iter().map(|i| c[*i]).collect()),
+            profit_scenarios: self.profit_scenarios.as_ref()
+                .map(|s| s.iter().map(|p| permutation.iter().map(|i| p[*i]).collect()).collect()),
+            ..self.clone()
+        };
+
+        (canonical, permutation)
+    }
+}
+
+/// Summarizes the instance as its number of items, its capacity and its tightness
+/// (the ratio of the capacity to the total weight of the items)
+impl fmt::Display for KnapsackInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} items, capacity {}, tightness {:.3}", self.nb_items, self.unscale(self.capacity), self.tightness())
+    }
+}
+
+/// Generates small valid instances (at most 20 items, weights and profits between
+/// 1 and 100, capacity at most the total weight) for property-based tests, e.g.
+/// checking that the ddo solver agrees with the dp one.
+#[cfg(feature = "test-instances")]
+impl<'a> arbitrary::Arbitrary<'a> for KnapsackInstance {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let nb_items = u.int_in_range(0..=20)?;
+        let mut weight = Vec::with_capacity(nb_items);
+        let mut profit = Vec::with_capacity(nb_items);
+        for _ in 0..nb_items {
+            weight.push(u.int_in_range(1..=100)?);
+            profit.push(u.int_in_range(1..=100)?);
+        }
+        let capacity = u.int_in_range(0..=weight.iter().sum::<isize>())?;
+
+        Ok(KnapsackInstance {
+            nb_items,
+            capacity,
+            weight,
+            profit,
+            ..Default::default()
+        })
+    }
 }
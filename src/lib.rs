@@ -0,0 +1,22 @@
+//! The knapsack tools as a library: the instances and their formats, the
+//! generators, and the decision diagram based solver (see `resolution`), which
+//! can be embedded in other programs, e.g. with `solve_knapsack` or `solve_all`
+//! and a `SolverParams::cancel` flag to abort a resolution from another thread.
+
+#[macro_use]
+pub mod profiling;
+pub mod instance;
+pub mod generate;
+pub mod resolution;
+pub mod info;
+pub mod load;
+pub mod format;
+pub mod convert;
+pub mod bench;
+pub mod stats;
+pub mod perturb;
+pub mod solution;
+pub mod difficulty;
+pub mod scenarios;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
@@ -0,0 +1,206 @@
+//! This module gathers the functions used to read knapsack instances. They
+//! report a missing, unreadable or malformed input as an error describing it.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::format::Format;
+use crate::instance::KnapsackInstance;
+
+/// Reads the instance stored in the given file (or at the given URL when built
+/// with the `net` feature). When no format is given, it is guessed from the
+/// content of the file.
+pub fn load_instance(path: &str, format: Option<Format>) -> Result<KnapsackInstance, String> {
+    let content = read_text(path)?;
+    let format = format.or_else(|| Format::sniff(&content))
+        .ok_or_else(|| format!("cannot recognize the format of {path}, expected json, pisinger, csv or dimacs"))?;
+
+    let instance = format.parse(&content)
+        .map_err(|error| format!("cannot parse {path} as {format:?}: {error}"))?;
+    check_instance(path, instance)
+}
+
+/// Reads a dataset of instances. The file either holds a single instance object
+/// or an array of instance objects.
+pub fn load_dataset(path: &str) -> Result<Vec<KnapsackInstance>, String> {
+    let parse = |name: &str, instance: Value| -> Result<KnapsackInstance, String> {
+        let instance = serde_json::from_value(instance)
+            .map_err(|error| format!("cannot parse {name}: {error}"))?;
+        check_instance(name, instance)
+    };
+
+    match read_json(path)? {
+        Value::Array(instances) => instances.into_iter().enumerate()
+            .map(|(i, instance)| parse(&format!("{path}[{i}]"), instance))
+            .collect(),
+        instance => Ok(vec![parse(path, instance)?]),
+    }
+}
+
+/// Parses an instance given as a json string, e.g. on the command line
+pub fn parse_instance_json(json: &str) -> Result<KnapsackInstance, String> {
+    let instance = serde_json::from_str(json)
+        .map_err(|error| format!("malformed instance json: {error}"))?;
+    check_instance("given as json", instance)
+}
+
+/// Rejects an instance right away when it is malformed, rather than letting the
+/// solver fail later on.
+fn check_instance(name: &str, instance: KnapsackInstance) -> Result<KnapsackInstance, String> {
+    instance.validate().map_err(|error| format!("invalid instance {name}: {error}"))?;
+    Ok(instance)
+}
+
+/// The items of a catalog shared among several instances, which only differ by
+/// their capacity. Instance files can also be used as catalogs.
+#[derive(Debug, Deserialize)]
+struct ItemPool {
+    weight: Vec<isize>,
+    profit: Vec<isize>,
+    #[serde(default)]
+    cluster: Option<Vec<usize>>,
+}
+
+/// Builds an instance from the items stored in the given file and the given capacity.
+pub fn compose_instance(items: &str, capacity: isize) -> Result<KnapsackInstance, String> {
+    let pool: ItemPool = serde_json::from_value(read_json(items)?)
+        .map_err(|error| format!("cannot read the items of {items}: {error}"))?;
+    let instance = KnapsackInstance {
+        nb_items: pool.weight.len(),
+        capacity,
+        weight: pool.weight,
+        profit: pool.profit,
+        cluster: pool.cluster,
+        ..Default::default()
+    };
+    instance.validate().map_err(|error| format!("invalid items {items}: {error}"))?;
+    Ok(instance)
+}
+
+/// Builds an instance from two files listing the weights and the profits of the
+/// items, one per line, and the given capacity.
+pub fn load_parallel_lists(weights: &str, profits: &str, capacity: isize) -> Result<KnapsackInstance, String> {
+    let weight = read_list(weights)?;
+    let profit = read_list(profits)?;
+    if weight.len() != profit.len() {
+        return Err(format!("{weights} lists {} weights but {profits} lists {} profits", weight.len(), profit.len()));
+    }
+
+    check_instance(weights, KnapsackInstance {
+        nb_items: weight.len(),
+        capacity,
+        weight,
+        profit,
+        ..Default::default()
+    })
+}
+
+fn read_list(path: &str) -> Result<Vec<isize>, String> {
+    read_text(path)?.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|l| l.parse().map_err(|_| format!("invalid value in {path}: {l}")))
+        .collect()
+}
+
+/// Overrides the weight and/or profit of some items of the instance with the ones
+/// given in a patch file. Each non-empty line of the file that does not start with
+/// `#` patches one item, e.g. `item 3 profit=5000 weight=12`. Returns a
+/// description of each applied patch.
+pub fn apply_patch(instance: &mut KnapsackInstance, path: &str) -> Result<Vec<String>, String> {
+    let mut applied = vec![];
+    for (n, line) in read_text(path)?.lines().enumerate().map(|(n, l)| (n + 1, l.trim())) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        let item: usize = match (tokens.next(), tokens.next().and_then(|t| t.parse().ok())) {
+            (Some("item"), Some(item)) if item < instance.nb_items => item,
+            _ => return Err(format!("{path}:{n}: expected `item <index> field=value...` with an index below {}", instance.nb_items)),
+        };
+
+        for token in tokens {
+            let (field, value) = token.split_once('=')
+                .ok_or_else(|| format!("{path}:{n}: expected field=value, found {token}"))?;
+            let value: isize = value.parse()
+                .map_err(|_| format!("{path}:{n}: invalid value {value}"))?;
+            if value < 0 {
+                return Err(format!("{path}:{n}: the {field} of item {item} cannot be negative"));
+            }
+
+            let old = match field {
+                "weight" => std::mem::replace(&mut instance.weight[item], value),
+                "profit" => std::mem::replace(&mut instance.profit[item], value),
+                _ => return Err(format!("{path}:{n}: unknown field {field}, expected weight or profit")),
+            };
+            applied.push(format!("item {item} {field} {old} -> {value}"));
+        }
+    }
+    Ok(applied)
+}
+
+/// Reads a capacity from a text file holding a single integer.
+pub fn load_capacity(path: &str) -> Result<isize, String> {
+    std::fs::read_to_string(path)
+        .map_err(|error| format!("cannot read {path}: {error}"))?
+        .trim().parse()
+        .map_err(|_| format!("the capacity file {path} must hold an integer"))
+}
+
+fn read_json(path: &str) -> Result<Value, String> {
+    serde_json::from_str(&read_text(path)?).map_err(|error| format!("cannot parse {path} as json: {error}"))
+}
+
+fn read_text(path: &str) -> Result<String, String> {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        fetch_text(path)
+    } else {
+        std::fs::read_to_string(path).map_err(|error| format!("cannot read {path}: {error}"))
+    }
+}
+
+#[cfg(feature = "net")]
+fn fetch_text(url: &str) -> Result<String, String> {
+    let response = ureq::get(url).call().map_err(|error| format!("cannot fetch {url}: {error}"))?;
+    response.into_string().map_err(|error| format!("cannot read {url}: {error}"))
+}
+
+#[cfg(not(feature = "net"))]
+fn fetch_text(url: &str) -> Result<String, String> {
+    Err(format!("cannot fetch {url}: fetching instances over HTTP requires the `net` feature"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_instance, load_parallel_lists};
+    use crate::format::Format;
+
+    /// Writes the given content to a fresh file of the temporary directory
+    fn temp_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("knapsack-{}-{name}", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn reject_mismatched_vectors() {
+        let path = temp_file("mismatched.json", r#"{"nb_items": 3, "capacity": 10, "weight": [1, 2, 3], "profit": [4, 5]}"#);
+        let error = load_instance(&path, Some(Format::Json)).unwrap_err();
+        assert!(error.contains("found 2 profits for 3 items"), "{error}");
+    }
+
+    #[test]
+    fn reject_mismatched_lists() {
+        let weights = temp_file("weights.txt", "1\n2\n3\n");
+        let profits = temp_file("profits.txt", "4\n5\n");
+        let error = load_parallel_lists(&weights, &profits, 10).unwrap_err();
+        assert!(error.contains("lists 3 weights but"), "{error}");
+    }
+
+    #[test]
+    fn report_unreadable_files() {
+        let error = load_instance("/nonexistent/instance.json", None).unwrap_err();
+        assert!(error.starts_with("cannot read /nonexistent/instance.json"), "{error}");
+    }
+}
@@ -1,10 +1,14 @@
 use clap::{Parser, Subcommand};
-use generate::KnapsackGenerator;
-use resolution::Solve;
-
-mod instance;
-mod generate;
-mod resolution;
+use knapsack::bench::Bench;
+use knapsack::convert::Convert;
+use knapsack::difficulty::DifficultyEstimate;
+use knapsack::generate::KnapsackGenerator;
+use knapsack::info::Info;
+use knapsack::perturb::Perturb;
+use knapsack::resolution::Solve;
+use knapsack::scenarios::Scenarios;
+use knapsack::stats::Stats;
+use knapsack::solution::Verify;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -17,13 +21,31 @@ struct KnapsackTools {
 #[derive(Debug, Subcommand)]
 enum Command {
     Generate(KnapsackGenerator),
-    Solve(Solve)
+    Solve(Solve),
+    Info(Info),
+    Bench(Bench),
+    Convert(Convert),
+    Stats(Stats),
+    Perturb(Perturb),
+    Verify(Verify),
+    DifficultyEstimate(DifficultyEstimate),
+    Scenarios(Scenarios),
 }
 
 fn main() {
     let cli = KnapsackTools::parse();
+    #[cfg(feature = "profiling")]
+    let _flame = knapsack::profiling::init();
     match cli.command {
         Command::Generate(mut generate) => generate.generate(),
-        Command::Solve(solve) => solve.solve()
+        Command::Solve(solve) => solve.solve(),
+        Command::Info(info) => info.info(),
+        Command::Bench(bench) => bench.bench(),
+        Command::Convert(convert) => convert.convert(),
+        Command::Stats(stats) => stats.stats(),
+        Command::Perturb(perturb) => perturb.perturb(),
+        Command::Verify(verify) => verify.verify(),
+        Command::DifficultyEstimate(estimate) => estimate.estimate(),
+        Command::Scenarios(scenarios) => scenarios.scenarios(),
     }
 }
@@ -0,0 +1,76 @@
+use std::{fs::File, io::Write, time::{SystemTime, UNIX_EPOCH}};
+
+use clap::Args;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use rand_distr::{Distribution, Uniform};
+
+use crate::format::Format;
+use crate::generate::indexed_path;
+use crate::instance::KnapsackInstance;
+use crate::load::load_instance;
+
+#[derive(Debug, Args)]
+pub struct Perturb {
+    /// The path to the instance file
+    #[clap(short, long)]
+    pub instance: String,
+    /// The format of the instance file (guessed from its content when omitted)
+    #[clap(short, long, value_enum)]
+    pub format: Option<Format>,
+    /// The number of perturbed variants to produce
+    #[clap(short='k', long, default_value="10")]
+    pub count: usize,
+    /// The max relative change (in percent) applied to each weight and profit
+    #[clap(short, long, default_value="5")]
+    pub magnitude: f64,
+    /// An optional seed to kickstart the perturbation
+    #[clap(short, long)]
+    pub seed: Option<u64>,
+    /// Name of the files where to write the variants (suffixed with their index)
+    #[clap(short, long)]
+    pub output: Option<String>,
+}
+
+impl Perturb {
+    pub fn perturb(&self) {
+        let instance = load_instance(&self.instance, self.format).unwrap_or_else(|error| panic!("{error}"));
+
+        let seed = self.seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64);
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+
+        for index in 0..self.count {
+            let variant = self.variant(&instance, &mut rng, index);
+            let serialized = serde_json::to_string_pretty(&variant).unwrap();
+
+            match self.output_path(index) {
+                Some(output) => File::create(output).unwrap().write_all(serialized.as_bytes()).unwrap(),
+                None => println!("{serialized}"),
+            }
+        }
+    }
+
+    /// Multiplies each weight and profit by a factor drawn uniformly in
+    /// [1 - magnitude, 1 + magnitude], rounding the result and keeping the weights
+    /// non-negative. The capacity is left untouched.
+    fn variant(&self, instance: &KnapsackInstance, rng: &mut ChaChaRng, index: usize) -> KnapsackInstance {
+        let magnitude = self.magnitude / 100.0;
+        let factor = Uniform::new_inclusive(1.0 - magnitude, 1.0 + magnitude);
+        let mut jitter = |v: isize| (v as f64 * factor.sample(rng)).round() as isize;
+
+        let mut variant = instance.clone();
+        variant.weight.iter_mut().for_each(|w| *w = jitter(*w).max(0));
+        variant.profit.iter_mut().for_each(|p| *p = jitter(*p));
+
+        variant.metadata.insert("perturbed_from".to_string(), self.instance.clone().into());
+        variant.metadata.insert("perturbation".to_string(), self.magnitude.into());
+        variant.metadata.insert("perturbation_index".to_string(), index.into());
+        variant
+    }
+
+    /// The file where the variant with the given index is written: `name.json`
+    /// becomes `name_<index>.json`.
+    fn output_path(&self, index: usize) -> Option<String> {
+        self.output.as_ref().map(|output| indexed_path(output, index))
+    }
+}
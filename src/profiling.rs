@@ -0,0 +1,27 @@
+//! This module gathers the instrumentation enabled by the `profiling` feature.
+//! The solver phases are wrapped in `tracing` spans, which are recorded in
+//! `tracing.folded` so that `inferno-flamegraph` can turn them into a flamegraph.
+//! Without the feature, the spans compile to nothing.
+
+/// Enters a span named after the given phase until the end of the current block
+#[cfg(feature = "profiling")]
+macro_rules! profile_span {
+    ($name:literal) => {
+        let _span = tracing::trace_span!($name).entered();
+    };
+}
+
+#[cfg(not(feature = "profiling"))]
+macro_rules! profile_span {
+    ($name:literal) => {};
+}
+
+/// Records the spans in `tracing.folded` until the returned guard is dropped
+#[cfg(feature = "profiling")]
+pub fn init() -> tracing_flame::FlushGuard<std::io::BufWriter<std::fs::File>> {
+    use tracing_subscriber::prelude::*;
+
+    let (flame, guard) = tracing_flame::FlameLayer::with_file("./tracing.folded").unwrap();
+    tracing_subscriber::registry().with(flame).init();
+    guard
+}
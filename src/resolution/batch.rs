@@ -0,0 +1,30 @@
+//! This module exposes the resolution of many instances at once, which is the
+//! programmatic counterpart of the bench command.
+
+use crate::instance::KnapsackInstance;
+use crate::resolution::model::Knapsack;
+use crate::resolution::solve::{SolveResult, SolverParams, solve_knapsack};
+
+/// Solves each of the given instances with the given parameters. A malformed
+/// instance yields an error in its slot of the result instead of aborting the
+/// whole batch. When `parallel` is set (which requires the `rayon` feature), the
+/// instances are solved concurrently; bear in mind that each resolution already
+/// uses all the available cores.
+pub fn solve_all(instances: impl Iterator<Item = KnapsackInstance>, params: &SolverParams, parallel: bool) -> Vec<Result<SolveResult, String>> {
+    #[cfg(feature = "rayon")]
+    if parallel {
+        use rayon::prelude::*;
+        let instances = instances.collect::<Vec<KnapsackInstance>>();
+        return instances.into_par_iter().map(|instance| solve_one(instance, params)).collect();
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    assert!(!parallel, "solving instances in parallel requires the `rayon` feature");
+
+    instances.map(|instance| solve_one(instance, params)).collect()
+}
+
+fn solve_one(instance: KnapsackInstance, params: &SolverParams) -> Result<SolveResult, String> {
+    instance.validate()?;
+    Ok(solve_knapsack(&Knapsack::new(instance), params))
+}
@@ -0,0 +1,48 @@
+//! This module defines the criterion used to stop the resolution before the
+//! optimality of the best solution is proven.
+
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::time::Duration;
+
+use ddo::{Cutoff, TimeBudget};
+
+use crate::resolution::model::Knapsack;
+
+/// Stops the resolution when the time budget is exhausted, when the given number
+/// of nodes have been expanded or when the resolution is cancelled, whichever comes
+/// first. Unlike the time budget, the node budget yields the same result regardless
+/// of the speed of the machine.
+pub struct KnapsackCutoff<'a> {
+    time: TimeBudget,
+    max_nodes: Option<usize>,
+    problem: &'a Knapsack,
+    start: usize,
+    cancel: Option<Arc<AtomicBool>>,
+}
+
+impl <'a> KnapsackCutoff<'a> {
+    pub fn new(problem: &'a Knapsack, timeout: Duration, max_nodes: Option<usize>) -> Self {
+        KnapsackCutoff {
+            time: TimeBudget::new(timeout),
+            max_nodes,
+            problem,
+            start: problem.nb_explored(),
+            cancel: None,
+        }
+    }
+
+    /// Also stops the resolution as soon as the given flag is set, e.g. from another
+    /// thread. The best solution found so far is then reported as inexact.
+    pub fn with_cancellation(mut self, cancel: Option<Arc<AtomicBool>>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+}
+
+impl Cutoff for KnapsackCutoff<'_> {
+    fn must_stop(&self) -> bool {
+        self.time.must_stop()
+            || self.max_nodes.map(|max| self.problem.nb_explored() - self.start >= max).unwrap_or(false)
+            || self.cancel.as_ref().map(|c| c.load(Ordering::Relaxed)).unwrap_or(false)
+    }
+}
@@ -0,0 +1,41 @@
+//! This module implements the textbook dynamic programming algorithm for the
+//! 0/1 knapsack problem. It runs in O(n * capacity) time and memory, which
+//! restricts it to instances with a modest capacity, but its simplicity makes it
+//! a convenient reference to cross-check the decision diagram based solver.
+
+use crate::instance::KnapsackInstance;
+
+/// Computes the optimal value of the instance and the items of an optimal solution.
+pub fn solve_dp(instance: &KnapsackInstance) -> (isize, Vec<usize>) {
+    let capacity = usize::try_from(instance.capacity).expect("the capacity must be non-negative");
+    let n = instance.nb_items;
+
+    // best[c] is the best value achievable with the items considered so far and
+    // a capacity of c, take[i * (capacity + 1) + c] tells whether item i is part
+    // of that best solution
+    let mut best = vec![0_isize; capacity + 1];
+    let mut take = vec![false; n * (capacity + 1)];
+
+    for i in 0..n {
+        let weight = instance.weight[i] as usize;
+        let profit = instance.profit[i];
+        for c in (weight..=capacity).rev() {
+            if best[c - weight] + profit > best[c] {
+                best[c] = best[c - weight] + profit;
+                take[i * (capacity + 1) + c] = true;
+            }
+        }
+    }
+
+    let mut selected = vec![];
+    let mut c = capacity;
+    for i in (0..n).rev() {
+        if take[i * (capacity + 1) + c] {
+            selected.push(i);
+            c -= instance.weight[i] as usize;
+        }
+    }
+    selected.reverse();
+
+    (best[capacity], selected)
+}
@@ -0,0 +1,277 @@
+//! This module implements an exhaustive enumeration of the solutions of the
+//! knapsack model whose value reaches a given threshold. It walks the same
+//! transitions as the decision diagrams but prunes the search with the relaxation
+//! bound only, which makes it suited to post-optimum analyses.
+
+use ddo::{Decision, Problem, Relaxation, Variable};
+
+use crate::resolution::model::{Knapsack, KnapsackRelax, KnapsackState};
+
+/// The outcome of an enumeration
+#[derive(Debug, Clone)]
+pub struct Enumeration {
+    /// The solutions found together with their value
+    pub solutions: Vec<(isize, Vec<Decision>)>,
+    /// Whether the enumeration stopped before exploring all candidate solutions
+    pub truncated: bool,
+}
+
+struct Enumerator<'a> {
+    problem: &'a Knapsack,
+    relaxation: &'a KnapsackRelax,
+    threshold: isize,
+    limit: usize,
+    current: Vec<Decision>,
+    result: Enumeration,
+}
+
+/// Enumerates the solutions whose value is at least `threshold`, stopping as soon
+/// as more than `limit` of them exist.
+pub fn enumerate(problem: &Knapsack, relaxation: &KnapsackRelax, threshold: isize, limit: usize) -> Enumeration {
+    let mut enumerator = Enumerator {
+        problem,
+        relaxation,
+        threshold,
+        limit,
+        current: vec![],
+        result: Enumeration { solutions: vec![], truncated: false },
+    };
+    enumerator.explore(problem.initial_state(), problem.initial_value());
+    enumerator.result
+}
+
+impl Enumerator<'_> {
+    fn explore(&mut self, state: KnapsackState, value: isize) {
+        if self.result.truncated || value + self.relaxation.fast_upper_bound(&state) < self.threshold {
+            return;
+        }
+
+        match self.problem.next_variable(state.depth, &mut std::iter::empty()) {
+            None => {
+                if value >= self.threshold {
+                    if self.result.solutions.len() == self.limit {
+                        self.result.truncated = true;
+                    } else {
+                        self.result.solutions.push((value, self.current.clone()));
+                    }
+                }
+            },
+            Some(variable) => {
+                let mut decisions = vec![];
+                self.problem.for_each_in_domain(variable, &state, &mut |d| decisions.push(d));
+
+                for decision in decisions {
+                    let next = self.problem.transition(&state, decision);
+                    let cost = self.problem.transition_cost(&state, decision);
+                    self.current.push(decision);
+                    self.explore(next, value + cost);
+                    self.current.pop();
+                }
+            },
+        }
+    }
+}
+
+struct Counter<'a> {
+    problem: &'a Knapsack,
+    relaxation: &'a KnapsackRelax,
+    threshold: isize,
+    limit: usize,
+    count: usize,
+    capped: bool,
+}
+
+/// Counts the feasible solutions whose value is at least `threshold`, stopping as
+/// soon as more than `limit` of them exist. Returns the count and whether it was
+/// capped. Unlike `enumerate`, the solutions are not stored.
+pub fn count_solutions(problem: &Knapsack, relaxation: &KnapsackRelax, threshold: isize, limit: usize) -> (usize, bool) {
+    let mut counter = Counter {
+        problem,
+        relaxation,
+        threshold,
+        limit,
+        count: 0,
+        capped: false,
+    };
+    counter.explore(problem.initial_state(), problem.initial_value());
+    (counter.count, counter.capped)
+}
+
+impl Counter<'_> {
+    fn explore(&mut self, state: KnapsackState, value: isize) {
+        if self.capped || value + self.relaxation.fast_upper_bound(&state) < self.threshold {
+            return;
+        }
+
+        match self.problem.next_variable(state.depth, &mut std::iter::empty()) {
+            None => {
+                if value >= self.threshold {
+                    if self.count == self.limit {
+                        self.capped = true;
+                    } else {
+                        self.count += 1;
+                    }
+                }
+            },
+            Some(variable) => {
+                let mut decisions = vec![];
+                self.problem.for_each_in_domain(variable, &state, &mut |d| decisions.push(d));
+
+                for decision in decisions {
+                    let next = self.problem.transition(&state, decision);
+                    let cost = self.problem.transition_cost(&state, decision);
+                    self.explore(next, value + cost);
+                }
+            },
+        }
+    }
+}
+
+struct LightestSearch<'a> {
+    problem: &'a Knapsack,
+    relaxation: &'a KnapsackRelax,
+    target: isize,
+    current: Vec<Decision>,
+    best: Option<(isize, Vec<Decision>)>,
+}
+
+/// Searches the solution whose value is at least `target` and whose total weight
+/// is minimal. Returns that weight along with the solution, if any.
+pub fn lightest_solution(problem: &Knapsack, relaxation: &KnapsackRelax, target: isize) -> Option<(isize, Vec<Decision>)> {
+    let mut search = LightestSearch {
+        problem,
+        relaxation,
+        target,
+        current: vec![],
+        best: None,
+    };
+    search.explore(problem.initial_state(), problem.initial_value());
+    search.best
+}
+
+impl LightestSearch<'_> {
+    fn explore(&mut self, state: KnapsackState, value: isize) {
+        let weight = self.problem.instance.capacity - state.capacity as isize;
+        if self.best.as_ref().map(|(w, _)| weight >= *w).unwrap_or(false)
+            || value + self.relaxation.fast_upper_bound(&state) < self.target {
+            return;
+        }
+
+        match self.problem.next_variable(state.depth, &mut std::iter::empty()) {
+            None => {
+                if value >= self.target {
+                    self.best = Some((weight, self.current.clone()));
+                }
+            },
+            Some(variable) => {
+                let mut decisions = vec![];
+                self.problem.for_each_in_domain(variable, &state, &mut |d| decisions.push(d));
+
+                for decision in decisions {
+                    let next = self.problem.transition(&state, decision);
+                    let cost = self.problem.transition_cost(&state, decision);
+                    self.current.push(decision);
+                    self.explore(next, value + cost);
+                    self.current.pop();
+                }
+            },
+        }
+    }
+}
+
+struct IndexSearch<'a> {
+    problem: &'a Knapsack,
+    /// Any state, only used to compute the cost of the decisions
+    root: KnapsackState,
+    target: isize,
+    current: Vec<Decision>,
+}
+
+/// Searches the solution whose value is at least `target` and that selects the
+/// smallest first item index, then the smallest second index, and so on. The
+/// items are decided by increasing index, taking each item first: the first
+/// solution reaching the target is thus the lexicographically smallest one. The
+/// limit on the number of clusters is not enforced.
+pub fn lexicographic_solution(problem: &Knapsack, target: isize) -> Option<Vec<Decision>> {
+    let capacity = usize::try_from(problem.instance.capacity).ok()?;
+    let mut search = IndexSearch { problem, root: problem.initial_state(), target, current: vec![] };
+    if search.explore(0, capacity, problem.initial_value()) {
+        Some(search.current)
+    } else {
+        None
+    }
+}
+
+impl IndexSearch<'_> {
+    fn explore(&mut self, item: usize, capacity: usize, value: isize) -> bool {
+        if value + self.bound(item, capacity) < self.target {
+            return false;
+        }
+        if item == self.problem.instance.nb_items {
+            return value >= self.target;
+        }
+
+        let weight = self.problem.instance.weight[item] as usize;
+        for taken in [1, 0] {
+            if taken == 1 && weight > capacity {
+                continue;
+            }
+            let decision = Decision { variable: Variable(item), value: taken };
+            let cost = self.problem.transition_cost(&self.root, decision);
+            self.current.push(decision);
+            if self.explore(item + 1, capacity - taken as usize * weight, value + cost) {
+                return true;
+            }
+            self.current.pop();
+        }
+        false
+    }
+
+    /// The Dantzig bound of the items from the given index on
+    fn bound(&self, first: usize, mut capacity: usize) -> isize {
+        let mut bound = 0;
+        for &item in self.problem.order().iter().filter(|i| **i >= first) {
+            let weight = self.problem.instance.weight[item] as usize;
+            let profit = self.problem.transition_cost(&self.root, Decision { variable: Variable(item), value: 1 });
+            if profit <= 0 {
+                break;
+            }
+            if weight <= capacity {
+                bound += profit;
+                capacity -= weight;
+            } else {
+                bound += (capacity as f64 / weight as f64 * profit as f64).floor() as isize;
+                break;
+            }
+        }
+        bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ddo::Decision;
+
+    use super::lexicographic_solution;
+    use crate::instance::KnapsackInstance;
+    use crate::resolution::{Knapsack, selected_items, solve_dp};
+
+    fn lexicographic_optimum(capacity: isize, weight: Vec<isize>, profit: Vec<isize>) -> Vec<usize> {
+        let instance = KnapsackInstance { nb_items: weight.len(), capacity, weight, profit, ..Default::default() };
+        let (optimum, _) = solve_dp(&instance);
+        let problem = Knapsack::new(instance);
+        let solution: Vec<Decision> = lexicographic_solution(&problem, optimum).unwrap();
+        let selected = selected_items(&solution);
+        assert_eq!(problem.value_of(&selected), optimum);
+        selected
+    }
+
+    #[test]
+    fn smallest_indices_among_several_optima() {
+        assert_eq!(lexicographic_optimum(5, vec![5, 4], vec![5, 5]), vec![0]);
+        assert_eq!(lexicographic_optimum(5, vec![5, 2, 3], vec![5, 2, 3]), vec![0]);
+        assert_eq!(lexicographic_optimum(5, vec![2, 3, 5], vec![2, 3, 5]), vec![0, 1]);
+        assert_eq!(lexicographic_optimum(10, vec![6, 4, 5, 5], vec![6, 4, 5, 5]), vec![0, 1]);
+        assert_eq!(lexicographic_optimum(0, vec![3, 0, 0], vec![9, 1, 1]), vec![1, 2]);
+    }
+}
@@ -0,0 +1,28 @@
+//! This module formats the outcome of a resolution as metrics in the prometheus
+//! text exposition format, so that they can be scraped or pushed to a gateway.
+
+use std::fmt::Write;
+
+use crate::resolution::SolveResult;
+
+/// Formats the statistics of the given resolution as prometheus metrics.
+pub fn prometheus_metrics(result: &SolveResult) -> String {
+    let mut out = String::new();
+    let mut gauge = |name: &str, help: &str, value: f64| {
+        writeln!(out, "# HELP knapsack_{name} {help}").unwrap();
+        writeln!(out, "# TYPE knapsack_{name} gauge").unwrap();
+        writeln!(out, "knapsack_{name} {value}").unwrap();
+    };
+
+    let best_value = result.best_value.map(|v| v as f64).unwrap_or(f64::NAN);
+    let upper_bound = result.upper_bound as f64;
+
+    gauge("solve_duration_seconds", "Time spent solving the instance", result.duration.as_secs_f64());
+    gauge("best_value", "Value of the best solution found", best_value);
+    gauge("upper_bound", "Best upper bound proven on the optimal value", upper_bound);
+    gauge("gap", "Relative gap between the best value and the upper bound", (upper_bound - best_value) / upper_bound.abs().max(1.0));
+    gauge("explored_nodes", "Number of nodes expanded during the resolution", result.explored as f64);
+    gauge("exact", "Whether the best value was proven optimal", if result.is_exact { 1.0 } else { 0.0 });
+
+    out
+}
@@ -1,4 +1,23 @@
 mod solve;
 mod model;
+mod enumerate;
+mod dp;
+mod preprocess;
+mod cutoff;
+mod batch;
+mod multiple;
+mod restricted;
+#[cfg(feature = "metrics")]
+mod metrics;
 
-pub use solve::*;
\ No newline at end of file
+pub use solve::*;
+pub use model::*;
+pub use enumerate::*;
+pub use dp::*;
+pub use preprocess::*;
+pub use cutoff::*;
+pub use batch::*;
+pub use multiple::*;
+pub use restricted::*;
+#[cfg(feature = "metrics")]
+pub use metrics::*;
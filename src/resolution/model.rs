@@ -21,6 +21,8 @@
 //! using ddo. It is a fairly simple example but it features most of the aspects you will
 //! want to copy when implementing your own solver.
 //! 
+use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+
 use ddo::*;
 use ordered_float::OrderedFloat;
 
@@ -30,7 +32,31 @@ use crate::instance::KnapsackInstance;
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct KnapsackState {
     pub depth: usize,
-    pub capacity: isize,
+    pub capacity: usize,
+    /// The set of clusters from which an item was selected (only tracked when
+    /// the number of clusters is limited)
+    pub clusters: u128,
+    /// The number of items selected so far (only tracked when that number is limited)
+    pub selected: usize,
+    /// The number of items selected from each cluster, capped at the required
+    /// minimum (only tracked when a minimum per cluster is required)
+    pub per_cluster: Vec<usize>,
+}
+
+impl KnapsackState {
+    /// Tells whether this state dominates the other one, i.e. whether every
+    /// completion of the other state is also feasible from this state. This is
+    /// the case for states at the same depth when this state has at least the
+    /// same remaining capacity (equal states dominate each other), did not use
+    /// a cluster unused by the other one, did not select more items and did not
+    /// select fewer items from any cluster.
+    pub fn dominates(&self, other: &Self) -> bool {
+        self.depth == other.depth
+            && self.capacity >= other.capacity
+            && self.clusters & !other.clusters == 0
+            && self.selected <= other.selected
+            && self.per_cluster.iter().zip(other.per_cluster.iter()).all(|(a, b)| a >= b)
+    }
 }
 
 /// This structure describes a Knapsack instance
@@ -38,14 +64,302 @@ pub struct KnapsackState {
 pub struct Knapsack {
     pub instance: KnapsackInstance,
     order: Vec<usize>,
+    /// The weight of each item, which is non-negative in the standard 0/1 knapsack
+    weight: Vec<usize>,
+    /// The total weight of the items from each depth to the end of the order
+    remaining_weight: Vec<usize>,
+    /// Whether to skip the branch that leaves an item when all remaining items fit
+    forced_take: bool,
+    /// The maximum number of distinct clusters from which items can be selected
+    max_clusters: Option<usize>,
+    /// The maximum number of items that can be selected
+    at_most: Option<usize>,
+    /// The minimum number of items to select from each cluster
+    min_per_cluster: Option<usize>,
+    /// The number of items of each cluster from each depth to the end of the order
+    /// (only computed when a minimum per cluster is required)
+    cluster_remaining: Vec<Vec<usize>>,
+    /// The lightest weight of the items of each cluster from each depth to the end
+    /// of the order (only computed when a minimum per cluster is required)
+    cluster_lightest: Vec<Vec<usize>>,
+    /// The number of nodes expanded so far, shared among the clones of the problem
+    explored: Arc<AtomicUsize>,
+    /// The cost of each unit of capacity left unused
+    leftover_penalty: isize,
 }
 
 impl Knapsack {
     pub fn new(instance: KnapsackInstance) -> Self {
+        let weight = instance.weight.iter()
+            .map(|w| usize::try_from(*w).expect("the weights must be non-negative"))
+            .collect::<Vec<usize>>();
+
         let mut order = (0..instance.nb_items).collect::<Vec<usize>>();
-        order.sort_unstable_by_key(|i| OrderedFloat(- instance.profit[*i] as f64 / instance.weight[*i] as f64));
+        // items with the same ratio are sorted by index to make the order deterministic
+        order.sort_unstable_by_key(|i| (OrderedFloat(- instance.profit[*i] as f64 / instance.weight[*i] as f64), *i));
+
+        let mut remaining_weight = vec![0; instance.nb_items + 1];
+        for depth in (0..instance.nb_items).rev() {
+            remaining_weight[depth] = remaining_weight[depth + 1] + weight[order[depth]];
+        }
+
+        Knapsack { instance, order, weight, remaining_weight, forced_take: false, max_clusters: None, at_most: None, min_per_cluster: None, cluster_remaining: vec![], cluster_lightest: vec![], explored: Default::default(), leftover_penalty: 0 }
+    }
+
+    /// When all the remaining items fit in the knapsack, any solution that leaves
+    /// an item with a non-negative profit can be completed by taking that item
+    /// without losing feasibility nor value. Only emitting the take decision in
+    /// that case is thus sound and never prunes the optimum.
+    pub fn with_forced_take(mut self, forced_take: bool) -> Self {
+        self.forced_take = forced_take;
+        self
+    }
+
+    /// Limits the number of distinct clusters from which items can be selected.
+    /// The constraint is ignored when the instance does not tell the cluster of
+    /// its items.
+    pub fn with_max_clusters(mut self, max_clusters: Option<usize>) -> Self {
+        if let Some(cluster) = self.instance.cluster.as_ref() {
+            assert!(cluster.iter().all(|c| *c < 128), "at most 128 clusters are supported");
+            self.max_clusters = max_clusters;
+        }
+        self
+    }
+
+    /// Limits the number of items that can be selected
+    pub fn with_at_most(mut self, at_most: Option<usize>) -> Self {
+        self.at_most = at_most;
+        self
+    }
+
+    /// Requires at least `min` selected items from each of the `nb_clusters`
+    /// clusters, which are those of the original instance when this one is a
+    /// reduction (a cluster whose items were all removed still needs its minimum).
+    /// The constraint is ignored when the instance does not tell the cluster of its
+    /// items. Panics when the minimums cannot be met: a cluster has fewer than `min`
+    /// items, or the `min` lightest items of every cluster do not fit together.
+    pub fn with_min_per_cluster(mut self, min: Option<usize>, nb_clusters: usize) -> Self {
+        let (Some(min), Some(cluster)) = (min, self.instance.cluster.as_ref()) else {
+            return self;
+        };
+        assert!(nb_clusters >= self.instance.nb_clusters(), "an item belongs to a cluster beyond the {nb_clusters} clusters");
+
+        let mut weights = vec![vec![]; nb_clusters];
+        (0..self.instance.nb_items).for_each(|i| weights[cluster[i]].push(self.weight[i]));
+        let mut required = 0;
+        for (c, weights) in weights.iter_mut().enumerate() {
+            if weights.len() < min {
+                panic!("cluster {c} has {} items, fewer than the required {min}", weights.len());
+            }
+            weights.sort_unstable();
+            required += weights[..min].iter().sum::<usize>();
+        }
+        if required as isize > self.instance.capacity {
+            panic!("selecting {min} items per cluster needs a capacity of at least {required}, the capacity is {}", self.instance.capacity);
+        }
+
+        let n = self.instance.nb_items;
+        self.cluster_remaining = vec![vec![0; nb_clusters]; n + 1];
+        self.cluster_lightest = vec![vec![usize::MAX; nb_clusters]; n + 1];
+        for depth in (0..n).rev() {
+            let item = self.order[depth];
+            self.cluster_remaining[depth] = self.cluster_remaining[depth + 1].clone();
+            self.cluster_lightest[depth] = self.cluster_lightest[depth + 1].clone();
+            self.cluster_remaining[depth][cluster[item]] += 1;
+            let lightest = &mut self.cluster_lightest[depth][cluster[item]];
+            *lightest = (*lightest).min(self.weight[item]);
+        }
+        self.min_per_cluster = Some(min);
+        self
+    }
+
+    /// The number of items whose profit/weight ratio ties with another item, and
+    /// the number of groups of such items. The order of the tied items is only
+    /// decided by their index.
+    pub fn ratio_ties(&self) -> (usize, usize) {
+        let ratio = |i: usize| self.instance.profit[i] as f64 / self.instance.weight[i] as f64;
+
+        let mut tied_items = 0;
+        let mut tie_groups = 0;
+        for group in self.order.chunk_by(|a, b| ratio(*a) == ratio(*b)) {
+            if group.len() > 1 {
+                tied_items += group.len();
+                tie_groups += 1;
+            }
+        }
+        (tied_items, tie_groups)
+    }
+
+    /// The same model (with the same constraints) over another instance, typically
+    /// a reduction of this one. The count of expanded nodes is shared with this
+    /// problem.
+    pub fn restricted_to(&self, instance: KnapsackInstance) -> Self {
+        let mut problem = Knapsack::new(instance)
+            .with_forced_take(self.forced_take)
+            .with_max_clusters(self.max_clusters)
+            .with_at_most(self.at_most)
+            .with_min_per_cluster(self.min_per_cluster, self.nb_quota_clusters())
+            .with_leftover_penalty(self.leftover_penalty);
+        problem.explored = self.explored.clone();
+        problem
+    }
+
+    /// The items in the sequence in which the solver assigns them
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+
+    /// Subtracts `penalty * remaining capacity` from the value of the solutions. Since
+    /// the remaining capacity is the capacity minus the weight of the selected items,
+    /// this amounts to adding `penalty * weight` to the profit of each item and
+    /// starting from a value of `-penalty * capacity`. The model is thus unchanged
+    /// apart from its costs: the optimum proven by the solver is the optimum of the
+    /// penalized objective, which can differ from the max profit solution. Adding
+    /// the same amount per unit of weight to every ratio keeps the order of the
+    /// items, hence the Dantzig bound remains valid.
+    pub fn with_leftover_penalty(mut self, penalty: isize) -> Self {
+        assert!(penalty >= 0, "the leftover penalty must be non-negative");
+        self.leftover_penalty = penalty;
+        self
+    }
+
+    /// The profit of the given item in the objective, including the leftover penalty
+    fn effective_profit(&self, item: usize) -> isize {
+        self.instance.profit[item] + self.leftover_penalty * self.instance.weight[item]
+    }
+
+    /// The value of the solution selecting the given items in the objective of the
+    /// model (i.e. including the leftover penalty)
+    pub fn value_of(&self, selected: &[usize]) -> isize {
+        self.initial_value() + selected.iter().map(|i| self.effective_profit(*i)).sum::<isize>()
+    }
+
+    /// Tells whether selecting the given items satisfies every constraint of the
+    /// model: the capacity, the limits on the number of clusters and items, and the
+    /// minimums per cluster
+    pub fn is_feasible(&self, selected: &[usize]) -> bool {
+        let mut items = selected.to_vec();
+        items.sort_unstable();
+        items.dedup();
+        if items.len() != selected.len() || items.iter().any(|i| *i >= self.instance.nb_items) {
+            return false;
+        }
+        if items.iter().map(|i| self.instance.weight[*i]).sum::<isize>() > self.instance.capacity {
+            return false;
+        }
+        if self.at_most.is_some_and(|k| items.len() > k) {
+            return false;
+        }
+        let Some(cluster) = self.instance.cluster.as_ref() else {
+            return true;
+        };
+        let mut per_cluster = vec![0; self.nb_quota_clusters().max(self.instance.nb_clusters())];
+        items.iter().for_each(|i| per_cluster[cluster[*i]] += 1);
+        let nb_used = per_cluster.iter().filter(|count| **count > 0).count();
+        self.max_clusters.is_none_or(|max| nb_used <= max)
+            && self.min_per_cluster.is_none_or(|min| per_cluster[..self.nb_quota_clusters()].iter().all(|count| *count >= min))
+    }
+
+    /// The number of nodes expanded so far with this problem
+    pub fn nb_explored(&self) -> usize {
+        self.explored.load(Ordering::Relaxed)
+    }
+
+    /// Tells whether the cluster of the given item can still be used in the given state
+    fn cluster_allowed(&self, state: &KnapsackState, item: usize) -> bool {
+        match (self.max_clusters, self.instance.cluster.as_ref()) {
+            (Some(max), Some(cluster)) => {
+                state.clusters & (1_u128 << cluster[item]) != 0 || (state.clusters.count_ones() as usize) < max
+            },
+            _ => true,
+        }
+    }
+
+    /// Tells whether one more item can be selected in the given state
+    fn count_allowed(&self, state: &KnapsackState) -> bool {
+        self.at_most.is_none_or(|k| state.selected < k)
+    }
+
+    /// The number of clusters subject to a minimum (0 when no minimum is required)
+    fn nb_quota_clusters(&self) -> usize {
+        self.cluster_remaining.first().map_or(0, |r| r.len())
+    }
+
+    /// Tells whether the minimums per cluster can still be met from the given state:
+    /// each cluster must have enough remaining items to make up for its deficit, and
+    /// the lightest remaining item of each cluster times its deficit must fit in the
+    /// remaining capacity (which underestimates the capacity actually needed).
+    fn quotas_reachable(&self, state: &KnapsackState) -> bool {
+        let Some(min) = self.min_per_cluster else {
+            return true;
+        };
+        let remaining = &self.cluster_remaining[state.depth];
+        let lightest = &self.cluster_lightest[state.depth];
+
+        let mut needed = 0_usize;
+        for (c, count) in state.per_cluster.iter().enumerate() {
+            let deficit = min - count;
+            if deficit > 0 {
+                if remaining[c] < deficit {
+                    return false;
+                }
+                needed = needed.saturating_add(deficit.saturating_mul(lightest[c]));
+            }
+        }
+        needed <= state.capacity
+    }
+
+    /// Fills the knapsack by decreasing profit/weight ratio, taking every item
+    /// that still fits (up to the limit on the number of items, if any). Returns
+    /// the value of that solution and the selected items.
+    pub fn greedy(&self) -> (isize, Vec<usize>) {
+        self.top_ratio(self.at_most.unwrap_or(usize::MAX))
+    }
+
+    /// Solves the LP relaxation of the instance: the items are taken by decreasing
+    /// ratio until the break item, of which only the fraction that fits is taken.
+    /// Returns the value of that solution and the fraction of each item taken. This
+    /// is the same fill as the Dantzig bound, without rounding the value down.
+    pub fn lp_relaxation(&self) -> (f64, Vec<f64>) {
+        let mut capacity = self.instance.capacity as f64;
+        let mut value = 0.0;
+        let mut fraction = vec![0.0; self.instance.nb_items];
+
+        for &item in self.order.iter() {
+            if self.instance.profit[item] <= 0 {
+                break;
+            }
+            let weight = self.weight[item] as f64;
+            let taken = if weight <= capacity { 1.0 } else { capacity / weight };
+            fraction[item] = taken;
+            value += taken * self.instance.profit[item] as f64;
+            capacity -= taken * weight;
+            if taken < 1.0 {
+                break;
+            }
+        }
 
-        Knapsack { instance, order }
+        (value, fraction)
+    }
+
+    /// Same as `greedy`, but stops once `k` items are selected
+    pub fn top_ratio(&self, k: usize) -> (isize, Vec<usize>) {
+        let mut capacity = self.instance.capacity;
+        let mut value = 0;
+        let mut selected = vec![];
+
+        for &item in self.order.iter() {
+            if selected.len() >= k {
+                break;
+            }
+            if capacity >= self.instance.weight[item] {
+                capacity -= self.instance.weight[item];
+                value += self.instance.profit[item];
+                selected.push(item);
+            }
+        }
+
+        (value, selected)
     }
 }
 
@@ -59,27 +373,47 @@ impl Problem for Knapsack {
     fn initial_state(&self) -> Self::State {
         KnapsackState {
             depth: 0,
-            capacity: self.instance.capacity,
+            capacity: usize::try_from(self.instance.capacity).expect("the capacity must be non-negative"),
+            clusters: 0,
+            selected: 0,
+            per_cluster: vec![0; self.nb_quota_clusters()],
         }
     }
 
     fn initial_value(&self) -> isize {
-        0
+        - self.leftover_penalty * self.instance.capacity
     }
 
     fn transition(&self, state: &Self::State, decision: ddo::Decision) -> Self::State {
+        profile_span!("transition");
+        let mut clusters = state.clusters;
+        if let (Some(_), Some(cluster), 1) = (self.max_clusters, self.instance.cluster.as_ref(), decision.value) {
+            clusters |= 1_u128 << cluster[decision.variable.id()];
+        }
+        let mut per_cluster = state.per_cluster.clone();
+        if let (Some(min), Some(cluster), 1) = (self.min_per_cluster, self.instance.cluster.as_ref(), decision.value) {
+            let count = &mut per_cluster[cluster[decision.variable.id()]];
+            *count = (*count + 1).min(min);
+        }
+
         KnapsackState {
             depth: state.depth + 1,
-            capacity: state.capacity - decision.value * self.instance.weight[decision.variable.id()],
+            capacity: state.capacity
+                .checked_sub(decision.value as usize * self.weight[decision.variable.id()])
+                .expect("an item was taken while it does not fit"),
+            clusters,
+            selected: state.selected + (self.at_most.is_some() && decision.value == 1) as usize,
+            per_cluster,
         }
     }
 
     fn transition_cost(&self, _: &Self::State, decision: ddo::Decision) -> isize {
-        decision.value * self.instance.profit[decision.variable.id()]
+        decision.value * self.effective_profit(decision.variable.id())
     }
 
     fn next_variable(&self, depth: usize, _: &mut dyn Iterator<Item = &Self::State>)
         -> Option<ddo::Variable> {
+        profile_span!("next_variable");
         if depth < self.instance.nb_items {
             Some(Variable(self.order[depth]))
         } else {
@@ -88,22 +422,94 @@ impl Problem for Knapsack {
     }
 
     fn for_each_in_domain(&self, variable: ddo::Variable, state: &Self::State, f: &mut dyn ddo::DecisionCallback) {
-        f.apply(Decision {variable, value: 0});
+        self.explored.fetch_add(1, Ordering::Relaxed);
 
-        if state.capacity >= self.instance.weight[variable.id()] {
+        if self.forced_take
+            && self.max_clusters.is_none()
+            && self.at_most.is_none()
+            && self.min_per_cluster.is_none()
+            && state.capacity >= self.remaining_weight[state.depth]
+            && self.instance.profit[variable.id()] >= 0 {
             f.apply(Decision {variable, value: 1});
+            return;
         }
+
+        // with minimums per cluster, a decision is only emitted when they can still be met afterwards
+        let reachable = |decision| self.min_per_cluster.is_none() || self.quotas_reachable(&self.transition(state, decision));
+
+        let leave = Decision {variable, value: 0};
+        if reachable(leave) {
+            f.apply(leave);
+        }
+
+        let take = Decision {variable, value: 1};
+        if state.capacity >= self.weight[variable.id()] && self.cluster_allowed(state, variable.id()) && self.count_allowed(state) && reachable(take) {
+            f.apply(take);
+        }
+    }
+}
+
+/// The ways to compute the capacity of a merged state. The relaxation is only
+/// admissible (i.e. relaxed DDs never underestimate the optimum, which is what
+/// makes the pruning exact) when the merged state is at least as good as each of
+/// the merged states. Only `MaxCapacity` guarantees it, the other strategies are
+/// meant for experiments on the quality of the relaxed DDs and can yield a wrong
+/// optimum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum MergeStrategy {
+    /// keep the largest remaining capacity (admissible)
+    #[default]
+    MaxCapacity,
+    /// keep the smallest remaining capacity (not admissible)
+    MinCapacity,
+    /// keep the average remaining capacity (not admissible)
+    Average,
+}
+
+impl MergeStrategy {
+    pub fn is_admissible(&self) -> bool {
+        *self == MergeStrategy::MaxCapacity
     }
 }
 
 /// This structure implements the Knapsack relaxation
 pub struct KnapsackRelax {
-    pb: Knapsack
+    pb: Knapsack,
+    merge: MergeStrategy,
+    /// Whether the Dantzig bound is used, rather than the sum of the remaining profits
+    bound: bool,
+    /// The max profit of the items of each cluster from each depth to the end of
+    /// the order (only computed when a minimum per cluster is required)
+    cluster_best: Vec<Vec<isize>>,
 }
 
 impl KnapsackRelax {
     pub fn new(pb: Knapsack) -> Self {
-        KnapsackRelax { pb }
+        let mut cluster_best = vec![];
+        if let (Some(_), Some(cluster)) = (pb.min_per_cluster, pb.instance.cluster.as_ref()) {
+            let n = pb.instance.nb_items;
+            cluster_best = vec![vec![isize::MIN; pb.nb_quota_clusters()]; n + 1];
+            for depth in (0..n).rev() {
+                let item = pb.order[depth];
+                cluster_best[depth] = cluster_best[depth + 1].clone();
+                let best = &mut cluster_best[depth][cluster[item]];
+                *best = (*best).max(pb.effective_profit(item));
+            }
+        }
+        KnapsackRelax { pb, merge: MergeStrategy::default(), bound: true, cluster_best }
+    }
+
+    pub fn with_merge(mut self, merge: MergeStrategy) -> Self {
+        self.merge = merge;
+        self
+    }
+
+    /// Replaces the Dantzig bound by the trivial bound summing the positive profits
+    /// of the remaining items, which is still valid (the optimum is unchanged) but
+    /// prunes much less. This measures how much the bound speeds up the search.
+    pub fn with_bound(mut self, bound: bool) -> Self {
+        self.bound = bound;
+        self
     }
 }
 
@@ -111,38 +517,114 @@ impl Relaxation for KnapsackRelax {
     type State = KnapsackState;
 
     fn merge(&self, states: &mut dyn Iterator<Item = &Self::State>) -> Self::State {
-        let mut capacity = 0;
+        profile_span!("merge");
+        let mut max_capacity = 0;
+        let mut min_capacity = usize::MAX;
+        let mut total_capacity = 0;
+        let mut count = 0;
         let mut depth = 0;
+        let mut clusters = u128::MAX;
+        let mut selected = usize::MAX;
+        let mut per_cluster = vec![];
         for s in states {
-            capacity = capacity.max(s.capacity);
+            max_capacity = max_capacity.max(s.capacity);
+            min_capacity = min_capacity.min(s.capacity);
+            total_capacity += s.capacity;
+            count += 1;
             depth = depth.max(s.depth);
+            clusters &= s.clusters;
+            selected = selected.min(s.selected);
+            per_cluster.resize(s.per_cluster.len(), 0);
+            per_cluster.iter_mut().zip(s.per_cluster.iter()).for_each(|(c, s)| *c = (*c).max(*s));
         }
-        KnapsackState { depth, capacity }
+        let capacity = match self.merge {
+            MergeStrategy::MaxCapacity => max_capacity,
+            MergeStrategy::MinCapacity => min_capacity,
+            MergeStrategy::Average => total_capacity / count.max(1),
+        };
+        KnapsackState { depth, capacity, clusters, selected, per_cluster }
     }
 
-    fn relax(&self, _: &Self::State, _: &Self::State, _:  &Self::State, _: Decision, cost: isize) -> isize {
+    fn relax(&self, _: &Self::State, dest: &Self::State, merged: &Self::State, _: Decision, cost: isize) -> isize {
+        debug_assert!(!self.merge.is_admissible() || merged.dominates(dest), "the merged state must dominate the merged states");
         cost
     }
 
     fn fast_upper_bound(&self, state: &Self::State) -> isize {
-        let mut depth = state.depth;
-        let mut max_profit = 0;
-        let mut capacity = state.capacity;
+        profile_span!("fast_upper_bound");
+        let bound = if self.bound {
+            let bound = self.dantzig_bound(state.depth, state.capacity);
+            self.quota_bound(state).map_or(bound, |quota| bound.min(quota))
+        } else {
+            self.remaining_profit(state, usize::MAX)
+        };
+        match self.pb.at_most {
+            Some(k) => bound.min(self.remaining_profit(state, k.saturating_sub(state.selected))),
+            None => bound,
+        }
+    }
+}
+
+impl KnapsackRelax {
+    /// The sum of the `count` largest positive profits of the remaining items. When
+    /// at most `count` more items can be selected, this bounds the value of any
+    /// completion regardless of the capacity. Filling the knapsack by ratio with
+    /// `count` items would not: a single heavy item can be worth more than the
+    /// best ratio one.
+    fn remaining_profit(&self, state: &KnapsackState, count: usize) -> isize {
+        let mut profits = self.pb.order[state.depth..].iter()
+            .map(|i| self.pb.effective_profit(*i))
+            .filter(|p| *p > 0)
+            .collect::<Vec<isize>>();
+        if count < profits.len() {
+            profits.select_nth_unstable_by(count, |a, b| b.cmp(a));
+            profits.truncate(count);
+        }
+        profits.iter().sum()
+    }
 
-        while capacity > 0 && depth < self.pb.instance.nb_items {
-            let item = self.pb.order[depth];
+    /// Bounds the value of the completions that meet the minimums per cluster. The
+    /// items making up for the deficit of a cluster weigh at least as much as its
+    /// lightest remaining item and are worth at most its most profitable one, and
+    /// the other items fit in the capacity they leave, which the Dantzig bound
+    /// bounds. Returns None when no minimum is required.
+    fn quota_bound(&self, state: &KnapsackState) -> Option<isize> {
+        let min = self.pb.min_per_cluster?;
+        let lightest = &self.pb.cluster_lightest[state.depth];
+        let best = &self.cluster_best[state.depth];
 
-            if capacity >= self.pb.instance.weight[item] {
-                max_profit += self.pb.instance.profit[item];
-                capacity -= self.pb.instance.weight[item];
+        let mut needed = 0_usize;
+        let mut quota_profit = 0_isize;
+        for (c, count) in state.per_cluster.iter().enumerate() {
+            let deficit = min - count;
+            if deficit > 0 {
+                needed = needed.saturating_add(deficit.saturating_mul(lightest[c]));
+                quota_profit = quota_profit.saturating_add((deficit as isize).saturating_mul(best[c]));
+            }
+        }
+        Some(quota_profit.saturating_add(self.dantzig_bound(state.depth, state.capacity.saturating_sub(needed))))
+    }
+
+    /// Fills the given capacity with the items from the given depth by decreasing
+    /// ratio, taking the fraction of the break item that fits. The items without
+    /// weight come first in the order, and are taken even when no capacity remains.
+    /// The items without profit come last, and are never worth taking.
+    fn dantzig_bound(&self, depth: usize, mut capacity: usize) -> isize {
+        let mut max_profit = 0;
+
+        for &item in self.pb.order[depth..].iter() {
+            if self.pb.effective_profit(item) <= 0 {
+                break;
+            }
+            if capacity >= self.pb.weight[item] {
+                max_profit += self.pb.effective_profit(item);
+                capacity -= self.pb.weight[item];
             } else {
-                let item_ratio = capacity as f64 / self.pb.instance.weight[item] as f64;
-                let item_profit = item_ratio * self.pb.instance.profit[item] as f64;
+                let item_ratio = capacity as f64 / self.pb.weight[item] as f64;
+                let item_profit = item_ratio * self.pb.effective_profit(item) as f64;
                 max_profit += item_profit.floor() as isize;
-                capacity = 0;
+                break;
             }
-
-            depth += 1;
         }
 
         max_profit
@@ -160,5 +642,170 @@ impl StateRanking for KnapsackRanking {
 
     fn compare(&self, a: &Self::State, b: &Self::State) -> std::cmp::Ordering {
         a.capacity.cmp(&b.capacity)
+            .then_with(|| b.clusters.count_ones().cmp(&a.clusters.count_ones()))
+            .then_with(|| b.selected.cmp(&a.selected))
+            .then_with(|| a.per_cluster.iter().sum::<usize>().cmp(&b.per_cluster.iter().sum::<usize>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use ddo::{Problem, Relaxation};
+
+    use super::{Knapsack, KnapsackRelax, KnapsackState, MergeStrategy};
+    use crate::instance::KnapsackInstance;
+    use crate::resolution::{SolverParams, solve_dp, solve_knapsack};
+
+    /// A random instance whose capacity is the given fraction of the total weight
+    fn random_instance(seed: u64, nb_items: usize, tightness: f64) -> KnapsackInstance {
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+        let weight = (0..nb_items).map(|_| rng.gen_range(1..=100)).collect::<Vec<isize>>();
+        let profit = (0..nb_items).map(|_| rng.gen_range(0..=100)).collect();
+        let capacity = (tightness * weight.iter().sum::<isize>() as f64) as isize;
+        KnapsackInstance { nb_items, capacity, weight, profit, ..Default::default() }
+    }
+
+    #[test]
+    fn forced_take_finds_the_optimum() {
+        for seed in 0..20 {
+            for tightness in [0.3, 0.8, 0.95] {
+                let instance = random_instance(seed, 25, tightness);
+                let (optimum, _) = solve_dp(&instance);
+                let problem = Knapsack::new(instance).with_forced_take(true);
+                let result = solve_knapsack(&problem, &SolverParams::default());
+                assert!(result.is_exact);
+                assert_eq!(result.best_value, Some(optimum), "seed {seed}, tightness {tightness}");
+            }
+        }
+    }
+
+    #[test]
+    fn default_merge_never_underestimates() {
+        for seed in 0..20 {
+            let instance = random_instance(seed, 30, 0.5);
+            let (optimum, _) = solve_dp(&instance);
+            let problem = Knapsack::new(instance);
+            // a tiny width forces the relaxed DDs to merge states
+            let result = solve_knapsack(&problem, &SolverParams { width: 2, ..Default::default() });
+            assert!(result.is_exact);
+            assert_eq!(result.best_value, Some(optimum), "seed {seed}");
+            assert!(result.upper_bound >= optimum, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn inadmissible_merges_are_inexact() {
+        let problem = Knapsack::new(random_instance(0, 30, 0.5));
+        for merge in [MergeStrategy::MinCapacity, MergeStrategy::Average] {
+            let result = solve_knapsack(&problem, &SolverParams { width: 2, merge, ..Default::default() });
+            assert!(!result.is_exact, "{merge:?}");
+        }
+    }
+
+    #[test]
+    fn lp_relaxation_skips_the_unprofitable_items() {
+        let instance = KnapsackInstance { nb_items: 3, capacity: 10, weight: vec![4, 2, 8], profit: vec![8, -1, 4], ..Default::default() };
+        let (value, fraction) = Knapsack::new(instance).lp_relaxation();
+        assert_eq!(value, 11.0);
+        assert_eq!(fraction, vec![1.0, 0.0, 0.75]);
+
+        let instance = KnapsackInstance { nb_items: 3, capacity: 20, weight: vec![4, 2, 8], profit: vec![8, -1, 4], ..Default::default() };
+        let (value, fraction) = Knapsack::new(instance).lp_relaxation();
+        assert_eq!(value, 12.0);
+        assert_eq!(fraction, vec![1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn lp_relaxation_at_zero_capacity() {
+        let instance = KnapsackInstance { nb_items: 3, capacity: 0, weight: vec![3, 0, 0], profit: vec![6, 5, -2], ..Default::default() };
+        let (value, fraction) = Knapsack::new(instance).lp_relaxation();
+        assert_eq!(value, 5.0);
+        assert_eq!(fraction, vec![0.0, 1.0, 0.0]);
+    }
+
+    fn state(capacity: usize, clusters: u128, selected: usize, per_cluster: Vec<usize>) -> KnapsackState {
+        KnapsackState { depth: 3, capacity, clusters, selected, per_cluster }
+    }
+
+    #[test]
+    fn equal_states_dominate_each_other() {
+        let a = state(10, 0b01, 2, vec![1, 0]);
+        assert!(a.dominates(&a.clone()));
+    }
+
+    #[test]
+    fn strict_dominance() {
+        let weaker = state(10, 0b11, 3, vec![1, 0]);
+        assert!(state(12, 0b11, 3, vec![1, 0]).dominates(&weaker));
+        assert!(state(10, 0b01, 3, vec![1, 0]).dominates(&weaker));
+        assert!(state(10, 0b11, 2, vec![1, 0]).dominates(&weaker));
+        assert!(state(10, 0b11, 3, vec![1, 1]).dominates(&weaker));
+        assert!(!weaker.dominates(&state(12, 0b11, 3, vec![1, 0])));
+
+        // each state is better on one criterion
+        assert!(!state(12, 0b11, 4, vec![1, 0]).dominates(&weaker));
+        assert!(!state(10, 0b100, 3, vec![1, 0]).dominates(&weaker));
+        assert!(!state(12, 0b11, 3, vec![0, 1]).dominates(&weaker));
+        assert!(!KnapsackState { depth: 4, ..weaker.clone() }.dominates(&weaker));
+    }
+
+    /// The best value of the subsets of items that fit and that are accepted by the
+    /// given predicate, by brute force
+    fn brute_force(instance: &KnapsackInstance, accept: impl Fn(&[usize]) -> bool) -> Option<isize> {
+        (0_usize..1 << instance.nb_items)
+            .map(|subset| (0..instance.nb_items).filter(|i| subset & (1 << i) != 0).collect::<Vec<usize>>())
+            .filter(|selected| selected.iter().map(|i| instance.weight[*i]).sum::<isize>() <= instance.capacity)
+            .filter(|selected| accept(selected.as_slice()))
+            .map(|selected| selected.iter().map(|i| instance.profit[*i]).sum::<isize>())
+            .max()
+    }
+
+    #[test]
+    fn binding_cap_on_the_number_of_items() {
+        let instance = KnapsackInstance { nb_items: 4, capacity: 13, weight: vec![1, 1, 1, 10], profit: vec![5, 5, 5, 12], ..Default::default() };
+        for (k, expected) in [(1, 12), (2, 17), (3, 22), (4, 27)] {
+            let problem = Knapsack::new(instance.clone()).with_at_most(Some(k));
+            let result = solve_knapsack(&problem, &SolverParams::default());
+            assert!(result.is_exact);
+            assert_eq!(result.best_value, Some(expected), "k {k}");
+        }
+
+        for seed in 0..10 {
+            let instance = random_instance(seed, 12, 0.6);
+            for k in [1, 3, 5] {
+                let problem = Knapsack::new(instance.clone()).with_at_most(Some(k));
+                let result = solve_knapsack(&problem, &SolverParams::default());
+                assert_eq!(result.best_value, brute_force(&instance, |selected| selected.len() <= k), "seed {seed}, k {k}");
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cluster 2 has 0 items")]
+    fn emptied_cluster_cannot_meet_its_minimum() {
+        // the items of cluster 2 were removed from the original instance
+        let instance = KnapsackInstance { nb_items: 2, capacity: 10, weight: vec![1, 2], profit: vec![1, 2], cluster: Some(vec![0, 1]), ..Default::default() };
+        Knapsack::new(instance).with_min_per_cluster(Some(1), 3);
+    }
+
+    #[test]
+    fn minimums_per_cluster_tighten_the_bound() {
+        for seed in 0..10 {
+            let mut instance = random_instance(seed, 12, 0.6);
+            instance.cluster = Some((0..12).map(|i| i % 3).collect());
+            let optimum = brute_force(&instance, |selected| (0..3).all(|c| selected.iter().filter(|i| *i % 3 == c).count() >= 2));
+
+            let problem = Knapsack::new(instance.clone()).with_min_per_cluster(Some(2), 3);
+            let result = solve_knapsack(&problem, &SolverParams::default());
+            assert!(result.is_exact);
+            assert_eq!(result.best_value, optimum, "seed {seed}");
+
+            let root_bound = |problem: &Knapsack| KnapsackRelax::new(problem.clone()).fast_upper_bound(&problem.initial_state());
+            assert!(root_bound(&problem) >= optimum.unwrap(), "seed {seed}");
+            assert!(root_bound(&problem) <= root_bound(&Knapsack::new(instance)), "seed {seed}");
+        }
     }
 }
@@ -0,0 +1,275 @@
+//! This module implements a model of the multiple knapsack problem, in which
+//! the items are assigned to several knapsacks with their own capacity. Each item
+//! is either left out or assigned to one of the knapsacks in which it fits.
+
+use std::time::{Duration, Instant};
+
+use ddo::*;
+use ordered_float::OrderedFloat;
+
+use crate::instance::KnapsackInstance;
+use crate::resolution::solve::SolverParams;
+
+/// The state of the multiple knapsack model: the remaining capacity of each knapsack
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MultipleKnapsackState {
+    pub depth: usize,
+    pub capacities: Vec<usize>,
+}
+
+/// A multiple knapsack instance. A decision of value 0 leaves its item out, and a
+/// decision of value `j + 1` assigns its item to the knapsack `j`.
+#[derive(Debug, Clone)]
+pub struct MultipleKnapsack {
+    pub instance: KnapsackInstance,
+    order: Vec<usize>,
+    weight: Vec<usize>,
+    capacities: Vec<usize>,
+}
+
+impl MultipleKnapsack {
+    pub fn new(instance: KnapsackInstance) -> Self {
+        let capacities = instance.capacities.as_ref()
+            .expect("a multiple knapsack instance needs a list of capacities")
+            .iter()
+            .map(|c| usize::try_from(*c).expect("the capacities must be non-negative"))
+            .collect();
+        let weight = instance.weight.iter()
+            .map(|w| usize::try_from(*w).expect("the weights must be non-negative"))
+            .collect();
+
+        let mut order = (0..instance.nb_items).collect::<Vec<usize>>();
+        order.sort_unstable_by_key(|i| (OrderedFloat(- instance.profit[*i] as f64 / instance.weight[*i] as f64), *i));
+
+        MultipleKnapsack { instance, order, weight, capacities }
+    }
+}
+
+impl Problem for MultipleKnapsack {
+    type State = MultipleKnapsackState;
+
+    fn nb_variables(&self) -> usize {
+        self.instance.nb_items
+    }
+
+    fn initial_state(&self) -> Self::State {
+        MultipleKnapsackState { depth: 0, capacities: self.capacities.clone() }
+    }
+
+    fn initial_value(&self) -> isize {
+        0
+    }
+
+    fn transition(&self, state: &Self::State, decision: Decision) -> Self::State {
+        let mut capacities = state.capacities.clone();
+        if decision.value > 0 {
+            let bin = decision.value as usize - 1;
+            capacities[bin] -= self.weight[decision.variable.id()];
+        }
+        MultipleKnapsackState { depth: state.depth + 1, capacities }
+    }
+
+    fn transition_cost(&self, _: &Self::State, decision: Decision) -> isize {
+        if decision.value > 0 {
+            self.instance.profit[decision.variable.id()]
+        } else {
+            0
+        }
+    }
+
+    fn next_variable(&self, depth: usize, _: &mut dyn Iterator<Item = &Self::State>) -> Option<Variable> {
+        if depth < self.instance.nb_items {
+            Some(Variable(self.order[depth]))
+        } else {
+            None
+        }
+    }
+
+    fn for_each_in_domain(&self, variable: Variable, state: &Self::State, f: &mut dyn DecisionCallback) {
+        f.apply(Decision { variable, value: 0 });
+        for (bin, capacity) in state.capacities.iter().enumerate() {
+            if *capacity >= self.weight[variable.id()] {
+                f.apply(Decision { variable, value: bin as isize + 1 });
+            }
+        }
+    }
+}
+
+/// Merges states by keeping the largest remaining capacity of each knapsack, which
+/// yields a state that dominates all the merged ones.
+pub struct MultipleKnapsackRelax {
+    pb: MultipleKnapsack,
+}
+
+impl MultipleKnapsackRelax {
+    pub fn new(pb: MultipleKnapsack) -> Self {
+        MultipleKnapsackRelax { pb }
+    }
+}
+
+impl Relaxation for MultipleKnapsackRelax {
+    type State = MultipleKnapsackState;
+
+    fn merge(&self, states: &mut dyn Iterator<Item = &Self::State>) -> Self::State {
+        let mut depth = 0;
+        let mut capacities = vec![0; self.pb.capacities.len()];
+        for s in states {
+            depth = depth.max(s.depth);
+            capacities.iter_mut().zip(s.capacities.iter()).for_each(|(c, s)| *c = (*c).max(*s));
+        }
+        MultipleKnapsackState { depth, capacities }
+    }
+
+    fn relax(&self, _: &Self::State, _: &Self::State, _: &Self::State, _: Decision, cost: isize) -> isize {
+        cost
+    }
+
+    /// The Dantzig bound of a single knapsack whose capacity is the total remaining
+    /// capacity of all the knapsacks
+    fn fast_upper_bound(&self, state: &Self::State) -> isize {
+        let mut capacity = state.capacities.iter().sum::<usize>();
+        let mut max_profit = 0;
+
+        for &item in self.pb.order[state.depth..].iter() {
+            if self.pb.instance.profit[item] <= 0 {
+                break;
+            }
+            if capacity >= self.pb.weight[item] {
+                max_profit += self.pb.instance.profit[item];
+                capacity -= self.pb.weight[item];
+            } else {
+                let item_ratio = capacity as f64 / self.pb.weight[item] as f64;
+                max_profit += (item_ratio * self.pb.instance.profit[item] as f64).floor() as isize;
+                break;
+            }
+        }
+
+        max_profit
+    }
+}
+
+/// Ranks the states by their total remaining capacity
+pub struct MultipleKnapsackRanking;
+impl StateRanking for MultipleKnapsackRanking {
+    type State = MultipleKnapsackState;
+
+    fn compare(&self, a: &Self::State, b: &Self::State) -> std::cmp::Ordering {
+        a.capacities.iter().sum::<usize>().cmp(&b.capacities.iter().sum::<usize>())
+    }
+}
+
+/// The outcome of the resolution of a multiple knapsack instance
+#[derive(Debug, Clone)]
+pub struct MultipleSolveResult {
+    pub best_value: Option<isize>,
+    pub is_exact: bool,
+    /// The items assigned to each knapsack
+    pub bins: Vec<Vec<usize>>,
+    pub upper_bound: isize,
+    pub duration: Duration,
+}
+
+/// Solves the given multiple knapsack problem with decision diagrams. Only the
+/// width and the timeout of the parameters are used.
+pub fn solve_multiple(problem: &MultipleKnapsack, params: &SolverParams) -> MultipleSolveResult {
+    let relaxation = MultipleKnapsackRelax::new(problem.clone());
+    let width = FixedWidth(params.width);
+    let cutoff = TimeBudget::new(params.timeout);
+    let ranking = MultipleKnapsackRanking;
+    let mut fringe = NoDupFringe::new(MaxUB::new(&ranking));
+
+    let mut solver = ParBarrierSolverFc::new(problem, &relaxation, &ranking, &width, &cutoff, &mut fringe);
+
+    let start = Instant::now();
+    let Completion{best_value, is_exact} = solver.maximize();
+
+    let mut bins = vec![vec![]; problem.capacities.len()];
+    for decision in solver.best_solution().unwrap_or_default() {
+        if decision.value > 0 {
+            bins[decision.value as usize - 1].push(decision.variable.id());
+        }
+    }
+    bins.iter_mut().for_each(|b| b.sort_unstable());
+
+    MultipleSolveResult {
+        best_value,
+        is_exact,
+        bins,
+        upper_bound: solver.best_upper_bound(),
+        duration: start.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ddo::{Problem, Relaxation};
+
+    use super::{MultipleKnapsack, MultipleKnapsackRelax, solve_multiple};
+    use crate::instance::KnapsackInstance;
+    use crate::resolution::SolverParams;
+
+    fn instance(capacities: Vec<isize>, weight: Vec<isize>, profit: Vec<isize>) -> KnapsackInstance {
+        KnapsackInstance { nb_items: weight.len(), capacity: 0, weight, profit, capacities: Some(capacities), ..Default::default() }
+    }
+
+    /// The best value of the assignments of the items to the knapsacks, by brute force
+    fn brute_force(instance: &KnapsackInstance) -> isize {
+        let capacities = instance.capacities.as_ref().unwrap();
+        let nb_choices = capacities.len() + 1;
+        let mut best = 0;
+        for code in 0..nb_choices.pow(instance.nb_items as u32) {
+            let mut load = vec![0; capacities.len()];
+            let mut value = 0;
+            let mut rest = code;
+            for item in 0..instance.nb_items {
+                let choice = rest % nb_choices;
+                rest /= nb_choices;
+                if choice > 0 {
+                    load[choice - 1] += instance.weight[item];
+                    value += instance.profit[item];
+                }
+            }
+            if load.iter().zip(capacities).all(|(l, c)| l <= c) {
+                best = best.max(value);
+            }
+        }
+        best
+    }
+
+    fn root_bound(problem: &MultipleKnapsack) -> isize {
+        MultipleKnapsackRelax::new(problem.clone()).fast_upper_bound(&problem.initial_state())
+    }
+
+    #[test]
+    fn negative_profits_do_not_lower_the_bound() {
+        let problem = MultipleKnapsack::new(instance(vec![10], vec![5, 5], vec![10, -3]));
+        assert!(root_bound(&problem) >= 10);
+        assert_eq!(solve_multiple(&problem, &SolverParams::default()).best_value, Some(10));
+    }
+
+    #[test]
+    fn zero_weight_items_fit_in_empty_knapsacks() {
+        let problem = MultipleKnapsack::new(instance(vec![0, 0], vec![0, 0, 3], vec![4, 5, 6]));
+        assert!(root_bound(&problem) >= 9);
+        assert_eq!(solve_multiple(&problem, &SolverParams::default()).best_value, Some(9));
+    }
+
+    #[test]
+    fn agrees_with_brute_force() {
+        let cases = [
+            instance(vec![10, 6], vec![5, 5, 4, 0, 3, 7], vec![10, -3, 6, 2, 5, 9]),
+            instance(vec![0, 4], vec![0, 2, 2, 0, 5], vec![3, 4, -1, -2, 8]),
+            instance(vec![7, 7, 3], vec![3, 4, 0, 6, 2, 5], vec![-4, 7, 0, 9, 3, 6]),
+            instance(vec![5], vec![1, 2, 3, 0, 4, 5, 6], vec![2, 3, 4, 1, 5, -6, 7]),
+        ];
+        for (case, instance) in cases.into_iter().enumerate() {
+            let optimum = brute_force(&instance);
+            let problem = MultipleKnapsack::new(instance);
+            assert!(root_bound(&problem) >= optimum, "case {case}");
+            // a tiny width forces the relaxed DDs to merge states
+            let result = solve_multiple(&problem, &SolverParams { width: 2, ..Default::default() });
+            assert!(result.is_exact, "case {case}");
+            assert_eq!(result.best_value, Some(optimum), "case {case}");
+        }
+    }
+}
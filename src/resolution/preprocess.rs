@@ -0,0 +1,199 @@
+//! This module implements reductions which remove items from an instance before
+//! it is solved. A reduction remembers where each remaining item comes from so
+//! that the solutions of the reduced instance can be expressed in terms of the
+//! items of the original instance.
+
+use ddo::{Decision, Variable};
+use rand::{SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaChaRng;
+
+use crate::instance::KnapsackInstance;
+
+/// An instance restricted to a subset of the items of an original instance
+#[derive(Debug, Clone)]
+pub struct Reduction {
+    /// The reduced instance
+    pub instance: KnapsackInstance,
+    /// The index in the original instance of each item of the reduced instance
+    pub items: Vec<usize>,
+    /// The number of items of the original instance
+    nb_original: usize,
+}
+
+impl Reduction {
+    /// Creates a reduction that keeps all the items of the instance
+    pub fn new(instance: KnapsackInstance) -> Self {
+        let items = (0..instance.nb_items).collect();
+        let nb_original = instance.nb_items;
+        Reduction { instance, items, nb_original }
+    }
+
+    /// Only keeps the items of the reduced instance satisfying the given predicate
+    pub fn retain(self, keep: impl Fn(&KnapsackInstance, usize) -> bool) -> Self {
+        let kept = (0..self.instance.nb_items)
+            .filter(|i| keep(&self.instance, *i))
+            .collect::<Vec<usize>>();
+        self.select(kept)
+    }
+
+    /// Randomly permutes the items of the reduced instance with the given seed. The
+    /// optimal value does not depend on the order of the items, which makes it a
+    /// way to check that the solver does not either.
+    pub fn shuffle(self, seed: u64) -> Self {
+        let mut order = (0..self.instance.nb_items).collect::<Vec<usize>>();
+        order.shuffle(&mut ChaChaRng::seed_from_u64(seed));
+        self.select(order)
+    }
+
+    /// Keeps the given items of the reduced instance, in the given order
+    fn select(self, kept: Vec<usize>) -> Self {
+        let instance = KnapsackInstance {
+            nb_items: kept.len(),
+            capacity: self.instance.capacity,
+            weight: kept.iter().map(|i| self.instance.weight[*i]).collect(),
+            profit: kept.iter().map(|i| self.instance.profit[*i]).collect(),
+            cluster: self.instance.cluster.as_ref().map(|c| kept.iter().map(|i| c[*i]).collect()),
+            profit_scenarios: self.instance.profit_scenarios.as_ref()
+                .map(|s| s.iter().map(|p| kept.iter().map(|i| p[*i]).collect()).collect()),
+            ..self.instance.clone()
+        };
+        let items = kept.iter().map(|i| self.items[*i]).collect();
+
+        Reduction { instance, items, nb_original: self.nb_original }
+    }
+
+    /// Removes the items that are heavier than the capacity, since they can never
+    /// be part of a feasible solution.
+    pub fn remove_oversized(self) -> Self {
+        let capacity = self.instance.capacity;
+        self.retain(|instance, i| instance.weight[i] <= capacity)
+    }
+
+    /// Removes the items whose profit is below the given floor. Contrary to the
+    /// other reductions, this one is lossy: the optimum of the reduced instance can
+    /// be worse than the one of the original instance.
+    pub fn remove_unprofitable(self, floor: isize) -> Self {
+        self.retain(|instance, i| instance.profit[i] >= floor)
+    }
+
+    /// Removes the items that are dominated by another item. Item `a` dominates
+    /// item `b` when it is not heavier and not less profitable. Contrary to the
+    /// unbounded case, this is not enough to discard `b` in the 0/1 knapsack since
+    /// an optimal solution might take both items. Therefore `b` is only removed when
+    /// `a` and `b` do not fit together in the knapsack: any solution taking `b` can
+    /// then swap it for `a` without losing feasibility nor value. Ties between
+    /// identical items are broken by index so that exactly one of them is kept,
+    /// and the swaps always move towards items that come first in the order
+    /// (weight, -profit, index), which guarantees that at least one optimal
+    /// solution survives the reduction.
+    pub fn remove_dominated(self) -> Self {
+        let n = self.instance.nb_items;
+        let capacity = self.instance.capacity;
+        let weight = &self.instance.weight;
+        let profit = &self.instance.profit;
+
+        let mut sorted = (0..n).collect::<Vec<usize>>();
+        sorted.sort_unstable_by_key(|i| (weight[*i], -profit[*i], *i));
+
+        // sparse table answering max profit queries over ranges of sorted items
+        let mut table = vec![sorted.iter().map(|i| profit[*i]).collect::<Vec<isize>>()];
+        let mut len = 1;
+        while 2 * len <= n {
+            let prev = table.last().unwrap();
+            let next = (0..=(n - 2 * len)).map(|i| prev[i].max(prev[i + len])).collect();
+            table.push(next);
+            len *= 2;
+        }
+        let max_profit = |lo: usize, hi: usize| {
+            let k = (usize::BITS - 1 - (hi - lo).leading_zeros()) as usize;
+            table[k][lo].max(table[k][hi - (1 << k)])
+        };
+
+        // the items preceding b in the sorted order are not heavier than b, among
+        // them those heavier than capacity - w(b) do not fit together with b
+        let mut dominated = vec![false; n];
+        for (j, b) in sorted.iter().enumerate() {
+            let lo = sorted[..j].partition_point(|a| weight[*a] <= capacity - weight[*b]);
+            if lo < j && max_profit(lo, j) >= profit[*b] {
+                dominated[*b] = true;
+            }
+        }
+
+        self.retain(|_, i| !dominated[i])
+    }
+
+    /// The number of items removed from the original instance
+    pub fn nb_removed(&self) -> usize {
+        self.nb_original - self.instance.nb_items
+    }
+
+    /// Translates the given indices of items of the reduced instance into indices of
+    /// the original instance
+    pub fn original_items(&self, selected: &[usize]) -> Vec<usize> {
+        selected.iter().map(|i| self.items[*i]).collect()
+    }
+
+    /// Translates the given indices of items of the original instance into indices of
+    /// the reduced instance, or None when one of these items was removed
+    pub fn reduced_items(&self, selected: &[usize]) -> Option<Vec<usize>> {
+        selected.iter().map(|item| self.items.iter().position(|i| i == item)).collect()
+    }
+
+    /// Translates a solution of the reduced instance into a solution of the original
+    /// instance. The removed items are left out of the knapsack.
+    pub fn restore(&self, decisions: &[Decision]) -> Vec<Decision> {
+        let mut kept = vec![false; self.nb_original];
+        let mut restored = decisions.iter()
+            .map(|d| {
+                let item = self.items[d.variable.id()];
+                kept[item] = true;
+                Decision { variable: Variable(item), value: d.value }
+            })
+            .collect::<Vec<Decision>>();
+
+        for (item, _) in kept.iter().enumerate().filter(|(_, k)| !**k) {
+            restored.push(Decision { variable: Variable(item), value: 0 });
+        }
+
+        restored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::Reduction;
+    use crate::instance::KnapsackInstance;
+    use crate::resolution::solve_dp;
+
+    fn instance(capacity: isize, weight: Vec<isize>, profit: Vec<isize>) -> KnapsackInstance {
+        KnapsackInstance { nb_items: weight.len(), capacity, weight, profit, ..Default::default() }
+    }
+
+    #[test]
+    fn dominated_items_that_fit_together_are_kept() {
+        let reduction = Reduction::new(instance(10, vec![2, 3], vec![5, 4])).remove_dominated();
+        assert_eq!(reduction.items, vec![0, 1]);
+
+        let reduction = Reduction::new(instance(5, vec![5, 4], vec![5, 5])).remove_dominated();
+        assert_eq!(reduction.items, vec![1]);
+    }
+
+    #[test]
+    fn removing_dominated_items_keeps_the_optimum() {
+        let mut removed = 0;
+        for seed in 0..20 {
+            let mut rng = ChaChaRng::seed_from_u64(seed);
+            let weight = (0..30).map(|_| rng.gen_range(10..=60)).collect::<Vec<isize>>();
+            let profit = (0..30).map(|_| rng.gen_range(1..=20)).collect();
+            let original = instance(100, weight, profit);
+
+            let reduction = Reduction::new(original.clone()).remove_dominated();
+            removed += reduction.nb_removed();
+            assert_eq!(solve_dp(&reduction.instance).0, solve_dp(&original).0, "seed {seed}");
+        }
+        assert!(removed > 0);
+    }
+}
@@ -0,0 +1,88 @@
+//! This module implements a heuristic resolution that only compiles a restricted
+//! decision diagram, i.e. a beam search of the model. The layers are compiled top
+//! down and only the `width` most promising nodes of each layer are kept, the
+//! others are dropped rather than merged. No relaxed diagram is compiled, so the
+//! optimum is only proven when no node had to be dropped.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use ddo::{Cutoff, Decision, Problem, Relaxation, StateRanking};
+
+use crate::resolution::model::{Knapsack, KnapsackRanking, KnapsackRelax, KnapsackState};
+use crate::resolution::cutoff::KnapsackCutoff;
+use crate::resolution::solve::{SolveResult, SolverParams};
+
+/// A node of the restricted diagram, with the value and the decisions of the best
+/// path leading to it
+struct Node {
+    state: KnapsackState,
+    value: isize,
+    path: Vec<Decision>,
+}
+
+/// Compiles a restricted decision diagram of the given width. The best solution
+/// is exact when the diagram never had to drop a node. The reported upper bound
+/// is the bound of the relaxation at the root, since no relaxed diagram refines it.
+pub fn solve_restricted(problem: &Knapsack, params: &SolverParams) -> SolveResult {
+    let relaxation = KnapsackRelax::new(problem.clone()).with_bound(params.bound);
+    let cutoff = KnapsackCutoff::new(problem, params.timeout, params.max_nodes).with_cancellation(params.cancel.clone());
+    let ranking = KnapsackRanking;
+
+    let start = Instant::now();
+    let explored = problem.nb_explored();
+    let root = problem.initial_state();
+    let upper_bound = problem.initial_value() + relaxation.fast_upper_bound(&root);
+
+    let mut layer = vec![Node { state: root, value: problem.initial_value(), path: vec![] }];
+    let mut is_exact = true;
+    let mut depth = 0;
+
+    while let Some(variable) = problem.next_variable(depth, &mut layer.iter().map(|n| &n.state)) {
+        if cutoff.must_stop() {
+            return SolveResult {
+                best_value: None,
+                is_exact: false,
+                solution: None,
+                upper_bound,
+                duration: start.elapsed(),
+                explored: problem.nb_explored() - explored,
+            };
+        }
+
+        // the nodes reached with the same state are fused, keeping the best path
+        let mut next = HashMap::<KnapsackState, Node>::new();
+        for node in layer.iter() {
+            let mut decisions = vec![];
+            problem.for_each_in_domain(variable, &node.state, &mut |d| decisions.push(d));
+
+            for decision in decisions {
+                let state = problem.transition(&node.state, decision);
+                let value = node.value + problem.transition_cost(&node.state, decision);
+                if next.get(&state).is_none_or(|n| value > n.value) {
+                    let mut path = node.path.clone();
+                    path.push(decision);
+                    next.insert(state.clone(), Node { state, value, path });
+                }
+            }
+        }
+
+        layer = next.into_values().collect();
+        if layer.len() > params.width {
+            layer.sort_unstable_by(|a, b| b.value.cmp(&a.value).then_with(|| ranking.compare(&b.state, &a.state)));
+            layer.truncate(params.width.max(1));
+            is_exact = false;
+        }
+        depth += 1;
+    }
+
+    let best = layer.into_iter().max_by_key(|n| n.value);
+    SolveResult {
+        best_value: best.as_ref().map(|n| n.value),
+        is_exact,
+        upper_bound: if is_exact { best.as_ref().map_or(upper_bound, |n| n.value) } else { upper_bound },
+        solution: best.map(|n| n.path),
+        duration: start.elapsed(),
+        explored: problem.nb_explored() - explored,
+    }
+}
@@ -1,49 +1,1265 @@
-use std::{fs::File, io::BufReader, time::Duration};
+use std::sync::{Arc, atomic::AtomicBool};
+use std::time::{Duration, Instant};
 
-use clap::Args;
-use ddo::{FixedWidth, TimeBudget, NoDupFringe, MaxUB, ParBarrierSolverFc, Completion, Solver};
+use clap::{Args, ValueEnum};
+use serde::{Serialize, Deserialize};
+use ddo::{FixedWidth, NoDupFringe, SimpleFringe, Fringe, MaxUB, ParBarrierSolverFc, Completion, Solver, Decision, Variable, Problem, Relaxation};
 
-use crate::resolution::model::{Knapsack, KnapsackRelax, KnapsackRanking};
+use crate::resolution::model::{Knapsack, KnapsackRelax, KnapsackRanking, KnapsackState, MergeStrategy};
+use crate::resolution::enumerate::{enumerate, lightest_solution, lexicographic_solution, count_solutions, Enumeration};
+use crate::resolution::dp::solve_dp;
+use crate::resolution::cutoff::KnapsackCutoff;
+use crate::resolution::preprocess::Reduction;
+use crate::resolution::multiple::{MultipleKnapsack, solve_multiple};
+use crate::resolution::restricted::solve_restricted;
+#[cfg(feature = "metrics")]
+use crate::resolution::metrics::prometheus_metrics;
+use crate::format::Format;
+use crate::solution::SolutionFormat;
 use crate::instance::KnapsackInstance;
+use crate::load::{load_instance, load_capacity, compose_instance, load_parallel_lists, parse_instance_json, apply_patch};
 
 #[derive(Debug, Args)]
 pub struct Solve {
-    /// The path to the instance file
-    #[clap(short, long)]
-    pub instance: String,
+    /// The path to the instance file (or its URL when built with the `net` feature)
+    #[clap(short, long, required_unless_present_any = ["items_from", "weights", "instance_json"])]
+    pub instance: Option<String>,
+    /// The format of the instance file (guessed from its content when omitted)
+    #[clap(short, long, value_enum)]
+    pub format: Option<Format>,
+    /// Replace the capacity of the instance, either by an integer or by a percentage of the total weight (e.g. 50%)
+    #[clap(long)]
+    pub capacity_override: Option<String>,
+    /// A file overriding the weight or profit of some items, with lines such as `item 3 profit=5000`
+    #[clap(long)]
+    pub patch: Option<String>,
+    /// The instance itself, as a json string, to use instead of an instance file
+    #[clap(long, conflicts_with_all = ["instance", "items_from", "weights"])]
+    pub instance_json: Option<String>,
+    /// The path to a file holding the items (weights and profits) to use instead of an instance
+    #[clap(long, conflicts_with = "instance")]
+    pub items_from: Option<String>,
+    /// A file listing the weight of each item on its own line, to use along with --profits
+    #[clap(long, conflicts_with_all = ["instance", "items_from"], requires = "profits")]
+    pub weights: Option<String>,
+    /// A file listing the profit of each item on its own line, to use along with --weights
+    #[clap(long, requires = "weights")]
+    pub profits: Option<String>,
+    /// The capacity of the knapsack when the items are loaded with --items-from or --weights
+    #[clap(long, conflicts_with_all = ["instance", "capacity_from"])]
+    pub capacity: Option<isize>,
+    /// A file holding the capacity of the knapsack when the items are loaded with --items-from or --weights
+    #[clap(long, conflicts_with = "instance")]
+    pub capacity_from: Option<String>,
     /// max number of nodes in a layeer
     #[clap(short, long, default_value="100")]
     pub width: usize,
-    /// timeout
-    #[clap(short, long, default_value="60")]
+    /// timeout (in seconds)
+    #[clap(short, long, default_value="60", value_parser = clap::value_parser!(u64).range(1..))]
     pub timeout: u64,
+    /// timeout in milliseconds, for sub-second budgets
+    #[clap(long, conflicts_with = "timeout", value_parser = clap::value_parser!(u64).range(1..))]
+    pub timeout_ms: Option<u64>,
+    /// enumerate all the distinct optimal solutions
+    #[clap(long)]
+    pub all_optima: bool,
+    /// print how often each item appears in the solutions within this gap (in percent) of the best value
+    #[clap(long, value_name = "G", conflicts_with = "forced_take")]
+    pub near_optimal: Option<f64>,
+    /// max number of optimal solutions to enumerate
+    #[clap(long, default_value="100")]
+    pub max_optima: usize,
+    /// how to choose among several optimal solutions
+    #[clap(long, value_enum, default_value="any")]
+    pub tiebreak: TieBreak,
+    /// only take items (without branching) when all the remaining items fit
+    #[clap(long, conflicts_with = "all_optima")]
+    pub forced_take: bool,
+    /// max number of distinct clusters from which items can be selected
+    #[clap(long, conflicts_with = "compare_methods")]
+    pub max_clusters: Option<usize>,
+    /// print the number of groups of items with identical weight and profit, and the size of the largest one
+    #[clap(long)]
+    pub report_duplicates: bool,
+    /// print the Dantzig bound at the root, the greedy value and their gap before solving
+    #[clap(long, conflicts_with_all = ["json", "quiet"])]
+    pub print_bound_at_root: bool,
+    /// exit after printing the bounds at the root, without solving
+    #[clap(long, requires = "print_bound_at_root")]
+    pub bound_only: bool,
+    /// min number of items to select from each cluster (ignored when the instance has no clusters)
+    #[clap(long, value_name = "M", conflicts_with_all = ["compare_methods", "sweep_capacities", "objective", "multiple", "lp_relaxation", "select_top_ratio", "resume", "auto_warm_start", "remove_dominated"])]
+    pub min_per_cluster: Option<usize>,
+    /// max number of items that can be selected
+    #[clap(long, value_name = "K", conflicts_with_all = ["compare_methods", "sweep_capacities", "objective", "multiple", "lp_relaxation", "select_top_ratio", "resume"])]
+    pub at_most_k: Option<usize>,
+    /// whether to maximize the profit, or minimize it while covering the capacity
+    #[clap(long, value_enum, default_value="max", conflicts_with_all = ["compare_methods", "json", "max_clusters", "forced_take", "leftover_penalty", "tiebreak", "method", "remove_dominated", "profit_floor", "shuffle", "shuffle_seed", "solution_out", "resume", "all_optima", "objective_scale", "multiple", "lp_relaxation", "select_top_ratio", "near_optimal", "print_order"])]
+    pub objective: Objective,
+    /// the resolution method
+    #[clap(long, value_enum, default_value="ddo")]
+    pub method: Method,
+    /// max capacity of the instances that can be solved with the dp method
+    #[clap(long, default_value="100000")]
+    pub dp_max_capacity: isize,
+    /// solve with both ddo and dp and check that they agree on the optimal value
+    #[clap(long)]
+    pub compare_methods: bool,
+    /// solve the instance at each of these capacities (given as c1,c2,...), warm starting each resolution from the solution at the previous capacity
+    #[clap(long, value_delimiter = ',', conflicts_with_all = ["compare_methods", "json", "compare_width"])]
+    pub sweep_capacities: Option<Vec<isize>>,
+    /// solve with each of these widths (given as w1,w2,...) and print a csv table of the results
+    #[clap(long, value_delimiter = ',', conflicts_with_all = ["compare_methods", "json"])]
+    pub compare_width: Option<Vec<usize>>,
+    /// count the feasible subsets of items whose value is at least --min-value instead of optimizing
+    #[clap(long, conflicts_with_all = ["compare_methods", "json", "forced_take"])]
+    pub count_feasible: bool,
+    /// the min value of the subsets counted with --count-feasible
+    #[clap(long, default_value="0", requires = "count_feasible")]
+    pub min_value: isize,
+    /// the max number of subsets counted with --count-feasible
+    #[clap(long, default_value="1000000", requires = "count_feasible")]
+    pub max_count: usize,
+    /// max number of nodes expanded before the resolution stops
+    #[clap(long)]
+    pub max_nodes: Option<usize>,
+    /// how the relaxation merges states (only max-capacity guarantees exact results)
+    #[clap(long, value_enum, default_value="max-capacity")]
+    pub relax_merge: MergeStrategy,
+    /// with none, only compile restricted DDs (a beam search) that generally do not prove the optimum
+    #[clap(long, value_enum, default_value="full")]
+    pub relaxation: RelaxationMode,
+    /// replace the Dantzig bound by the sum of the remaining profits, to measure how much it helps
+    #[clap(long)]
+    pub no_bound: bool,
+    /// use a fringe that does not detect duplicate subproblems
+    #[clap(long)]
+    pub no_dedup: bool,
+    /// write the metrics of the resolution in the prometheus text format to this file
+    #[cfg(feature = "metrics")]
+    #[clap(long)]
+    pub metrics_file: Option<String>,
+    /// start from the incumbent saved in this file (if it exists), and save the best solution found there
+    #[clap(long, value_name = "STATE_FILE", conflicts_with = "max_clusters")]
+    pub resume: Option<String>,
+    /// subtract this penalty per unit of capacity left unused from the value of the solutions
+    #[clap(long, default_value="0", conflicts_with_all = ["compare_methods", "remove_dominated"])]
+    pub leftover_penalty: isize,
+    /// seed the search with the greedy solution as initial incumbent
+    #[clap(long, conflicts_with = "max_clusters")]
+    pub auto_warm_start: bool,
+    /// remove the items dominated by another item with which they do not fit
+    #[clap(long, conflicts_with_all = ["max_clusters", "all_optima", "near_optimal"])]
+    pub remove_dominated: bool,
+    /// drop the items whose profit is below this floor before solving (the result is then approximate)
+    #[clap(long)]
+    pub profit_floor: Option<isize>,
+    /// randomly permute the items with this seed before solving (the solution still uses the original indices)
+    #[clap(long)]
+    pub shuffle_seed: Option<u64>,
+    /// randomly permute the items before solving, using the seed given with --seed
+    #[clap(long, conflicts_with = "shuffle_seed")]
+    pub shuffle: bool,
+    /// the seed of every randomized choice, ignored when none is made (only --shuffle is randomized for now)
+    #[clap(long, default_value="0")]
+    pub seed: u64,
+    /// warn when the capacity is huge compared to the granularity of the weights
+    #[clap(long)]
+    pub integer_capacity_check: bool,
+    /// with --integer-capacity-check, fail instead of warning
+    #[clap(long, requires = "integer_capacity_check")]
+    pub strict: bool,
+    /// print details about the preprocessing
+    #[clap(short, long)]
+    pub verbose: bool,
+    /// only print the best value
+    #[clap(short, long)]
+    pub quiet: bool,
+    /// print the result as json
+    #[clap(long, conflicts_with = "quiet")]
+    pub json: bool,
+    /// write the best solution found to this file
+    #[clap(long)]
+    pub solution_out: Option<String>,
+    /// the format of the file written with --solution-out
+    #[clap(long, value_enum, default_value="json", requires = "solution_out")]
+    pub solution_format: SolutionFormat,
+    /// in the json result, only list the indices of the selected items
+    #[clap(long, requires = "json")]
+    pub sparse_solution: bool,
+    /// divide the reported values by this factor, e.g. 100 for profits given in cents (the search still uses the integers)
+    #[clap(long, value_name = "S")]
+    pub objective_scale: Option<f64>,
+    /// solve the LP relaxation instead and print the fraction of each item it takes
+    #[clap(long, conflicts_with_all = ["compare_methods", "json"])]
+    pub lp_relaxation: bool,
+    /// solve the instance as a multiple knapsack, assigning items to the knapsacks of its capacities list
+    #[clap(long, conflicts_with_all = ["compare_methods", "json", "max_nodes", "no_dedup", "forced_take", "leftover_penalty", "tiebreak", "all_optima", "max_clusters", "at_most_k", "min_per_cluster", "method", "remove_dominated", "profit_floor", "solution_out", "resume", "objective_scale", "near_optimal", "print_order"])]
+    pub multiple: bool,
+    /// only select the (at most) k items of best ratio that fit, and report that solution
+    #[clap(long, value_name = "K", conflicts_with_all = ["compare_methods", "json"])]
+    pub select_top_ratio: Option<usize>,
+    /// print the sequence (of original item indices) in which the items are assigned
+    #[clap(long)]
+    pub print_order: bool,
+    /// only check that the instance is well formed, without solving it (exits with a non-zero status otherwise)
+    #[clap(long)]
+    pub validate_only: bool,
+    /// exit with a non-zero status when the best value is not proven optimal
+    #[clap(long)]
+    pub fail_inexact: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Method {
+    /// branch-and-bound with decision diagrams
+    Ddo,
+    /// classic dynamic programming over the capacities
+    Dp,
+}
+
+/// Whether the branch-and-bound compiles relaxed decision diagrams
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RelaxationMode {
+    /// compile restricted and relaxed DDs, which proves the optimum
+    Full,
+    /// only compile a restricted DD, whose best solution is a heuristic one
+    None,
+}
+
+/// The direction of the optimization. The minimization is the covering knapsack:
+/// minimize the total profit (i.e. cost) of the selected items subject to their
+/// total weight being at least the capacity (i.e. requirement). It is solved as the
+/// maximization of the profit of the items left out, whose total weight must be at
+/// most the total weight minus the requirement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Objective {
+    /// maximize the profit subject to weight <= capacity
+    Max,
+    /// minimize the profit (cost) subject to weight >= capacity
+    Min,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TieBreak {
+    /// keep the optimal solution found by the solver
+    Any,
+    /// among the optimal solutions, keep one of minimum total weight
+    MinWeight,
+    /// among the optimal solutions, keep the one whose sorted item indices are lexicographically smallest
+    Index,
+}
+
+/// The outcome of the resolution of a knapsack instance
+#[derive(Debug, Clone)]
+pub struct SolveResult {
+    /// The value of the best solution found, if any
+    pub best_value: Option<isize>,
+    /// Whether the best value was proven optimal
+    pub is_exact: bool,
+    /// The decisions of the best solution found, if any
+    pub solution: Option<Vec<Decision>>,
+    /// The best upper bound proven on the optimal value
+    pub upper_bound: isize,
+    /// The time spent solving the instance
+    pub duration: Duration,
+    /// The number of nodes expanded during the resolution
+    pub explored: usize,
+}
+
+/// Summarizes the result as its best value and its bounds, e.g. `value 42 (exact)`
+/// or `value 40 in [40, 45] after 123 nodes`
+impl std::fmt::Display for SolveResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.best_value {
+            None if self.is_exact => write!(f, "no feasible solution"),
+            None => write!(f, "no solution found after {} nodes", self.explored),
+            Some(value) if self.is_exact => write!(f, "value {value} (exact)"),
+            Some(value) => write!(f, "value {value} in [{value}, {}] after {} nodes", self.upper_bound, self.explored),
+        }
+    }
+}
+
+/// The result of a resolution as printed with --json. The solution is either given
+/// as `solution`, the full 0/1 vector indexed by the original item indices, or
+/// with --sparse-solution as `selected`, the sorted indices of the selected items
+/// (the full vector has a 1 at these indices and a 0 everywhere else).
+#[derive(Debug, Serialize)]
+struct JsonOutput {
+    best_value: Option<isize>,
+    is_exact: bool,
+    /// The value of the best feasible solution found (the same as best_value)
+    lower_bound: Option<isize>,
+    /// The best upper bound proven on the optimal value, if any solution is feasible
+    upper_bound: Option<isize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    solution: Option<Vec<isize>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    selected: Option<Vec<usize>>,
+    /// The original indices of the items in the sequence in which they are assigned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<Vec<usize>>,
+    /// The number of items dropped by --profit-floor, when the result is approximate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dropped: Option<usize>,
+    /// The best value divided by --objective-scale
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scaled_best_value: Option<f64>,
+}
+
+/// The best solution of an inexact run, as saved with --resume. The fringe itself
+/// is not saved, only the incumbent that warm starts the next run.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedIncumbent {
+    best_value: isize,
+    is_exact: bool,
+    upper_bound: isize,
+    /// The sorted original indices of the selected items
+    selected: Vec<usize>,
+}
+
+/// The sorted indices of the items selected in the given solution
+pub fn selected_items(decisions: &[Decision]) -> Vec<usize> {
+    let mut selected = decisions.iter()
+        .filter(|d| d.value == 1)
+        .map(|d| d.variable.id())
+        .collect::<Vec<usize>>();
+    selected.sort_unstable();
+    selected
+}
+
+/// The parameters of the decision diagram based solver
+#[derive(Debug, Clone)]
+pub struct SolverParams {
+    /// The max number of nodes in a layer
+    pub width: usize,
+    /// The time budget of the resolution
+    pub timeout: Duration,
+    /// Whether the fringe discards duplicate subproblems
+    pub dedup: bool,
+    /// The max number of nodes expanded before the resolution stops
+    pub max_nodes: Option<usize>,
+    /// How the relaxation merges states
+    pub merge: MergeStrategy,
+    /// Whether the greedy solution is used as initial incumbent. It is skipped
+    /// when it violates a constraint on the clusters, which the greedy ignores.
+    pub warm_start: bool,
+    /// The items of a known feasible solution used as initial incumbent (when it
+    /// is better than the greedy one). It is ignored when it is not feasible.
+    pub incumbent: Option<Vec<usize>>,
+    /// Whether the relaxation uses the Dantzig bound (rather than a trivial one)
+    pub bound: bool,
+    /// A flag that aborts the resolution when it is set (e.g. from another thread)
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Whether only a restricted decision diagram is compiled (see `solve_restricted`)
+    pub restricted_only: bool,
+}
+
+impl Default for SolverParams {
+    fn default() -> Self {
+        SolverParams {
+            width: 100,
+            timeout: Duration::from_secs(60),
+            dedup: true,
+            max_nodes: None,
+            merge: MergeStrategy::default(),
+            warm_start: false,
+            incumbent: None,
+            bound: true,
+            cancel: None,
+            restricted_only: false,
+        }
+    }
+}
+
+/// Solves the given problem with decision diagrams, using the given parameters.
+pub fn solve_knapsack(problem: &Knapsack, params: &SolverParams) -> SolveResult {
+    if let Some(result) = trivial_result(problem) {
+        return result;
+    }
+
+    // the items heavier than the capacity can never be selected, the decision
+    // diagrams are smaller without them
+    let reduction = Reduction::new(problem.instance.clone()).remove_oversized();
+    if reduction.nb_removed() > 0 {
+        let reduced = problem.restricted_to(reduction.instance.clone());
+        let incumbent = params.incumbent.as_ref().and_then(|selected| reduction.reduced_items(selected));
+        let result = solve_knapsack(&reduced, &SolverParams { incumbent, ..params.clone() });
+        return SolveResult { solution: result.solution.map(|s| reduction.restore(&s)), ..result };
+    }
+
+    if params.restricted_only {
+        return solve_restricted(problem, params);
+    }
+
+    let relaxation = KnapsackRelax::new(problem.clone()).with_merge(params.merge).with_bound(params.bound);
+
+    let width = FixedWidth(params.width);
+    let cutoff = KnapsackCutoff::new(problem, params.timeout, params.max_nodes).with_cancellation(params.cancel.clone());
+    let ranking = KnapsackRanking;
+    let mut fringe: Box<dyn Fringe<State = KnapsackState> + Send + Sync> = if params.dedup {
+        Box::new(NoDupFringe::new(MaxUB::new(&ranking)))
+    } else {
+        Box::new(SimpleFringe::new(MaxUB::new(&ranking)))
+    };
+
+    let mut solver = ParBarrierSolverFc::new(problem, &relaxation, &ranking, &width, &cutoff, fringe.as_mut());
+    // a feasible solution is a valid lower bound: the search only prunes the nodes
+    // whose upper bound does not exceed it, which cannot hide a strictly better
+    // solution, and the given solution itself is kept when it turns out to be optimal.
+    // An infeasible one (e.g. the greedy solution, which ignores the constraints on
+    // the clusters) would claim a value that may not be reachable, it is ignored.
+    let mut primal = params.incumbent.as_ref()
+        .filter(|selected| problem.is_feasible(selected))
+        .map(|selected| (problem.value_of(selected), selected.clone()));
+    if params.warm_start {
+        let (_, selected) = problem.greedy();
+        let value = problem.value_of(&selected);
+        if problem.is_feasible(&selected) && primal.as_ref().is_none_or(|(v, _)| value > *v) {
+            primal = Some((value, selected));
+        }
+    }
+    if let Some((value, selected)) = primal {
+        solver.set_primal(value, decisions(problem.instance.nb_items, &selected));
+    }
+
+    let start = Instant::now();
+    let explored = problem.nb_explored();
+    let Completion{best_value, is_exact} = {
+        profile_span!("maximize");
+        solver.maximize()
+    };
+
+    SolveResult {
+        best_value,
+        // the pruning relies on the relaxed DDs never underestimating the optimum
+        is_exact: is_exact && params.merge.is_admissible(),
+        solution: solver.best_solution(),
+        upper_bound: solver.best_upper_bound(),
+        duration: start.elapsed(),
+        explored: problem.nb_explored() - explored,
+    }
+}
+
+/// The number of distinct remaining capacities above which --integer-capacity-check complains
+const MAX_DISTINCT_CAPACITIES: usize = 10_000_000;
+
+pub fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// The decisions selecting the given items among the given number of items
+fn decisions(nb_items: usize, selected: &[usize]) -> Vec<Decision> {
+    let mut solution = (0..nb_items)
+        .map(|i| Decision { variable: Variable(i), value: 0 })
+        .collect::<Vec<Decision>>();
+    selected.iter().for_each(|i| solution[*i].value = 1);
+    solution
+}
+
+/// Solves the degenerate instances without compiling any decision diagram. When the
+/// capacity is negative, no solution is feasible (not even the empty one), and when
+/// no item fits in the knapsack, the empty solution is optimal.
+fn trivial_result(problem: &Knapsack) -> Option<SolveResult> {
+    let instance = &problem.instance;
+
+    if instance.capacity < 0 {
+        Some(SolveResult {
+            best_value: None,
+            is_exact: true,
+            solution: None,
+            upper_bound: isize::MIN,
+            duration: Duration::ZERO,
+            explored: 0,
+        })
+    } else if instance.weight.iter().all(|w| *w > instance.capacity) {
+        Some(SolveResult {
+            best_value: Some(problem.value_of(&[])),
+            is_exact: true,
+            solution: Some(decisions(instance.nb_items, &[])),
+            upper_bound: problem.value_of(&[]),
+            duration: Duration::ZERO,
+            explored: 0,
+        })
+    } else {
+        None
+    }
 }
 
 impl Solve {
     pub fn solve(&self) {
-        let instance: KnapsackInstance = serde_json::from_reader(BufReader::new(File::open(&self.instance).unwrap())).unwrap();
-        
+        if self.validate_only {
+            self.validate_only();
+            return;
+        }
+
+        if self.tiebreak == TieBreak::Index && (self.shuffle || self.shuffle_seed.is_some() || self.remove_dominated || self.max_clusters.is_some() || self.at_most_k.is_some() || self.min_per_cluster.is_some()) {
+            panic!("the index tiebreak cannot be combined with shuffling, the removal of dominated items nor with constraints on the clusters or the number of items");
+        }
+
+        if self.forced_take && self.tiebreak != TieBreak::Any {
+            panic!("--forced-take skips some of the optimal solutions, it cannot be combined with a tiebreak");
+        }
+
+        if let Some(scale) = self.objective_scale {
+            assert!(scale > 0.0 && scale.is_finite(), "the objective scale must be positive, found {scale}");
+        }
+
+        let instance = self.load().unwrap_or_else(|error| panic!("{error}"));
+        if self.report_duplicates && !self.quiet && !self.json {
+            let (groups, largest) = instance.duplicate_groups();
+            println!("duplicate groups {groups}, largest group {largest}");
+        }
+        if self.integer_capacity_check {
+            self.check_capacity(&instance);
+        }
+        if self.print_bound_at_root {
+            Self::print_root_bounds(&instance);
+            if self.bound_only {
+                return;
+            }
+        }
+
+        if self.compare_methods {
+            self.compare_methods(instance);
+            return;
+        }
+        if self.count_feasible {
+            self.count_feasible(instance);
+            return;
+        }
+        if let Some(capacities) = self.sweep_capacities.as_ref() {
+            self.sweep_capacities(instance, capacities);
+            return;
+        }
+        if let Some(widths) = self.compare_width.as_ref() {
+            self.compare_width(instance, widths);
+            return;
+        }
+        if self.objective == Objective::Min {
+            self.solve_covering(instance);
+            return;
+        }
+        if self.multiple {
+            self.solve_multiple(instance);
+            return;
+        }
+        if self.lp_relaxation {
+            self.solve_lp_relaxation(instance);
+            return;
+        }
+        if let Some(k) = self.select_top_ratio {
+            self.select_top_ratio(instance, k);
+            return;
+        }
+
+        match self.method {
+            Method::Ddo => self.solve_ddo(instance),
+            Method::Dp => self.solve_dp(instance),
+        }
+    }
+
+    /// Loads the instance and reports whether it is well formed. The loaders already
+    /// validate the instances they read.
+    fn validate_only(&self) {
+        match self.load() {
+            Ok(instance) => println!("ok: {instance}"),
+            Err(error) => {
+                println!("invalid: {error}");
+                std::process::exit(1);
+            },
+        }
+    }
+
+    /// The remaining capacity of every state is the capacity minus a sum of weights,
+    /// so a layer holds at most capacity / gcd(weights) + 1 distinct states. This
+    /// warns (or fails with --strict) when that number makes the resolution hopeless.
+    fn check_capacity(&self, instance: &KnapsackInstance) {
+        let gcd = instance.weight.iter()
+            .fold(0_usize, |g, w| gcd(g, w.unsigned_abs()))
+            .max(1);
+        let nb_states = instance.capacity.max(0) as usize / gcd + 1;
+        if nb_states <= MAX_DISTINCT_CAPACITIES {
+            return;
+        }
+
+        let message = format!("the capacity {} allows up to {nb_states} distinct states per layer (weight gcd {gcd}), \
+            consider scaling the capacity and the weights down", instance.capacity);
+        if self.strict {
+            panic!("{message}");
+        }
+        eprintln!("warning: {message}");
+    }
+
+    /// Prints the cheapest bounds on the optimum: the greedy solution as lower bound
+    /// and the Dantzig bound at the root as upper bound
+    fn print_root_bounds(instance: &KnapsackInstance) {
+        if instance.capacity < 0 {
+            println!("no feasible solution");
+            return;
+        }
+
+        let problem = Knapsack::new(instance.clone());
+        let relaxation = KnapsackRelax::new(problem.clone());
+        let upper_bound = problem.initial_value() + relaxation.fast_upper_bound(&problem.initial_state());
+        let lower_bound = problem.value_of(&problem.greedy().1);
+        let gap = if upper_bound != 0 { 100.0 * (upper_bound - lower_bound) as f64 / upper_bound.abs() as f64 } else { 0.0 };
+
+        println!("root upper bound {upper_bound}");
+        println!("greedy lower bound {lower_bound}");
+        println!("root gap {gap:.2}%");
+    }
+
+    fn compare_methods(&self, instance: KnapsackInstance) {
+        let problem = Knapsack::new(instance.clone());
+        let start = Instant::now();
+        let result = solve_knapsack(&problem, &self.params());
+        let SolveResult{best_value, is_exact, ..} = result;
+        let ddo_time = start.elapsed().as_secs_f64();
+        println!("ddo {result} in {ddo_time:.3}s");
+
+        if instance.capacity > self.dp_max_capacity {
+            println!("dp skipped: the capacity {} exceeds {}", instance.capacity, self.dp_max_capacity);
+            return;
+        }
+        if instance.capacity < 0 {
+            println!("dp skipped: the capacity is negative");
+            return;
+        }
+
+        let start = Instant::now();
+        let (dp_value, _) = solve_dp(&instance);
+        let dp_time = start.elapsed().as_secs_f64();
+        println!("dp value {dp_value} in {dp_time:.3}s");
+
+        if !is_exact {
+            println!("ddo did not prove optimality, values cannot be compared");
+        } else if best_value != Some(dp_value) {
+            eprintln!("MISMATCH: ddo found {best_value:?} but the optimum is {dp_value}");
+            std::process::exit(1);
+        } else {
+            println!("methods agree");
+        }
+    }
+
+    fn count_feasible(&self, instance: KnapsackInstance) {
+        if instance.capacity < 0 {
+            println!("{}", if self.quiet { "0" } else { "count 0" });
+            return;
+        }
+
+        let nb_clusters = instance.nb_clusters();
+        let problem = Knapsack::new(instance)
+            .with_max_clusters(self.max_clusters)
+            .with_at_most(self.at_most_k)
+            .with_min_per_cluster(self.min_per_cluster, nb_clusters);
+        let relaxation = KnapsackRelax::new(problem.clone());
+        let (count, capped) = count_solutions(&problem, &relaxation, self.min_value, self.max_count);
+
+        if self.quiet {
+            println!("{count}");
+        } else {
+            println!("count {count}{}", if capped { " (capped)" } else { "" });
+        }
+    }
+
+    /// Solves the instance at increasing capacities. A solution feasible for a
+    /// capacity remains feasible for any larger one, so the solution at the previous
+    /// capacity is a valid incumbent, which never changes the optimum proven at the
+    /// next one. The sweep is then repeated with independent resolutions to report
+    /// the time saved by the reuse.
+    fn sweep_capacities(&self, instance: KnapsackInstance, capacities: &[isize]) {
+        let mut capacities = capacities.to_vec();
+        capacities.sort_unstable();
+
+        println!("capacity,best_value,is_exact,time_ms");
+        let mut incumbent = None;
+        let mut incremental = Duration::ZERO;
+        for capacity in capacities.iter() {
+            let problem = Knapsack::new(KnapsackInstance { capacity: *capacity, ..instance.clone() });
+            let result = solve_knapsack(&problem, &SolverParams { incumbent: incumbent.take(), ..self.params() });
+            incumbent = result.solution.as_ref().map(|s| selected_items(s));
+            incremental += result.duration;
+
+            let best_value = result.best_value.map(|v| v.to_string()).unwrap_or_default();
+            println!("{capacity},{best_value},{},{}", result.is_exact, result.duration.as_millis());
+        }
+
+        let independent = capacities.iter()
+            .map(|capacity| {
+                let problem = Knapsack::new(KnapsackInstance { capacity: *capacity, ..instance.clone() });
+                solve_knapsack(&problem, &self.params()).duration
+            })
+            .sum::<Duration>();
+
+        if !self.quiet {
+            eprintln!("incremental sweep {:.3}s, independent solves {:.3}s, saved {:.3}s",
+                incremental.as_secs_f64(), independent.as_secs_f64(),
+                independent.as_secs_f64() - incremental.as_secs_f64());
+        }
+    }
+
+    fn compare_width(&self, instance: KnapsackInstance, widths: &[usize]) {
+        let nb_clusters = instance.nb_clusters();
+        let problem = Knapsack::new(instance)
+            .with_forced_take(self.forced_take)
+            .with_max_clusters(self.max_clusters)
+            .with_at_most(self.at_most_k)
+            .with_min_per_cluster(self.min_per_cluster, nb_clusters);
+
+        println!("width,best_value,is_exact,explored,time_ms");
+        for width in widths {
+            let result = solve_knapsack(&problem, &SolverParams { width: *width, ..self.params() });
+            let best_value = result.best_value.map(|v| v.to_string()).unwrap_or_default();
+            println!("{width},{best_value},{},{},{}", result.is_exact, result.explored, result.duration.as_millis());
+        }
+    }
+
+    /// Solves the covering knapsack through its complement, see `Objective`
+    fn solve_covering(&self, instance: KnapsackInstance) {
+        let total_profit = instance.profit.iter().sum::<isize>();
+        let complement = KnapsackInstance { capacity: instance.total_weight() - instance.capacity, ..instance.clone() };
+        let result = solve_knapsack(&Knapsack::new(complement), &self.params());
+
+        let Some(best_value) = result.best_value else {
+            if self.quiet {
+                println!("none");
+            } else {
+                println!("is exact {}", result.is_exact);
+                println!("no feasible solution");
+            }
+            return;
+        };
+
+        let min_cost = total_profit - best_value;
+        if self.quiet {
+            println!("{min_cost}");
+        } else {
+            let left_out = selected_items(result.solution.as_ref().unwrap());
+            let selected = (0..instance.nb_items).filter(|i| left_out.binary_search(i).is_err());
+            println!("is exact {}", result.is_exact);
+            println!("min cost {min_cost}");
+            println!("bounds [{}, {min_cost}]", total_profit - result.upper_bound);
+            println!("selected items: {}", selected.map(|i| i.to_string()).collect::<Vec<_>>().join(" "));
+        }
+
+        if self.fail_inexact && !result.is_exact {
+            std::process::exit(1);
+        }
+    }
+
+    fn solve_multiple(&self, instance: KnapsackInstance) {
+        let problem = MultipleKnapsack::new(instance);
+        let result = solve_multiple(&problem, &self.params());
+
+        match result.best_value {
+            None => println!("{}", if self.quiet { "none" } else { "no feasible solution" }),
+            Some(best_value) if self.quiet => println!("{best_value}"),
+            Some(best_value) => {
+                println!("is exact {}", result.is_exact);
+                println!("best value {best_value}");
+                println!("bounds [{best_value}, {}]", result.upper_bound);
+                println!("solved in {:.3}s", result.duration.as_secs_f64());
+                for (bin, items) in result.bins.iter().enumerate() {
+                    let weight = items.iter().map(|i| problem.instance.weight[*i]).sum::<isize>();
+                    println!("knapsack {bin} (weight {weight}): {}", items.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(" "));
+                }
+            },
+        }
+
+        if self.fail_inexact && !result.is_exact {
+            std::process::exit(1);
+        }
+    }
+
+    fn solve_lp_relaxation(&self, instance: KnapsackInstance) {
+        if instance.capacity < 0 {
+            println!("{}", if self.quiet { "none" } else { "no feasible solution" });
+            return;
+        }
+
+        let (value, fraction) = Knapsack::new(instance).lp_relaxation();
+        if self.quiet {
+            println!("{value}");
+        } else {
+            println!("lp relaxation value {value} (an upper bound, not the integer optimum)");
+            println!("fractions: {}", fraction.iter().map(|f| f.to_string()).collect::<Vec<_>>().join(" "));
+        }
+    }
+
+    fn select_top_ratio(&self, instance: KnapsackInstance, k: usize) {
         let problem = Knapsack::new(instance);
+        let (value, selected) = problem.top_ratio(k);
+        let weight = selected.iter().map(|i| problem.instance.weight[*i]).sum::<isize>();
+
+        if self.quiet {
+            println!("{value}");
+        } else {
+            println!("feasible {}", weight <= problem.instance.capacity);
+            println!("value {value}");
+            println!("weight {}/{}", problem.instance.unscale(weight), problem.instance.unscale(problem.instance.capacity));
+            println!("selected items: {}", selected.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(" "));
+        }
+    }
+
+    fn solve_dp(&self, instance: KnapsackInstance) {
+        if self.leftover_penalty != 0 {
+            panic!("the dp method does not support the leftover penalty");
+        }
+        if self.max_clusters.is_some() || self.at_most_k.is_some() || self.min_per_cluster.is_some() {
+            panic!("the dp method does not support constraints on the clusters or the number of items");
+        }
+        if self.json {
+            panic!("the dp method does not support --json");
+        }
+        if instance.capacity > self.dp_max_capacity {
+            panic!("the capacity {} exceeds the limit of the dp method ({})", instance.capacity, self.dp_max_capacity);
+        }
+        if instance.capacity < 0 {
+            println!("{}", if self.quiet { "none" } else { "no feasible solution" });
+            return;
+        }
+
+        let (best_value, selected) = solve_dp(&instance);
+
+        if self.quiet {
+            println!("{best_value}");
+        } else {
+            println!("is exact true");
+            println!("best value {best_value}");
+            println!("selected items: {}", selected.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(" "));
+        }
+    }
+
+    fn solve_ddo(&self, instance: KnapsackInstance) {
+        // the reductions can remove every item of a cluster, which must still meet its minimum
+        let nb_clusters = instance.nb_clusters();
+        let reduction = match self.shuffle_seed.or(self.shuffle.then_some(self.seed)) {
+            Some(seed) => Reduction::new(instance).shuffle(seed),
+            None => Reduction::new(instance),
+        };
+        let reduction = reduction.remove_oversized();
+        if self.verbose && !self.json {
+            println!("removed {} oversized items", reduction.nb_removed());
+        }
+
+        let reduction = if self.remove_dominated {
+            let removed = reduction.nb_removed();
+            let reduction = reduction.remove_dominated();
+            if self.verbose && !self.json {
+                println!("removed {} dominated items", reduction.nb_removed() - removed);
+            }
+            reduction
+        } else {
+            reduction
+        };
+
+        let (reduction, dropped) = match self.profit_floor {
+            Some(floor) => {
+                let removed = reduction.nb_removed();
+                let reduction = reduction.remove_unprofitable(floor);
+                let dropped = reduction.nb_removed() - removed;
+                (reduction, (dropped > 0).then_some(dropped))
+            },
+            None => (reduction, None),
+        };
+        if let (Some(dropped), false, false) = (dropped, self.quiet, self.json) {
+            println!("approximate: dropped {dropped} items with a profit below {}", self.profit_floor.unwrap());
+        }
+
+        let problem = Knapsack::new(reduction.instance.clone())
+            .with_forced_take(self.forced_take)
+            .with_max_clusters(self.max_clusters)
+            .with_at_most(self.at_most_k)
+            .with_min_per_cluster(self.min_per_cluster, nb_clusters)
+            .with_leftover_penalty(self.leftover_penalty);
         let relaxation = KnapsackRelax::new(problem.clone());
 
-        let width = FixedWidth(self.width);
-        let cutoff = TimeBudget::new(Duration::from_secs(self.timeout));
-        let ranking = KnapsackRanking;
-        let mut fringe = NoDupFringe::new(MaxUB::new(&ranking));
+        if !self.relax_merge.is_admissible() {
+            eprintln!("warning: the {:?} merge strategy is not admissible, the result may not be optimal", self.relax_merge);
+        }
+
+        let params = SolverParams { incumbent: self.resumed_incumbent(&reduction), ..self.params() };
+        let mut result = solve_knapsack(&problem, &params);
+        // the optimum of the instance without the dropped items is not proven
+        // to be the optimum of the original instance
+        result.is_exact &= dropped.is_none();
+        if let Some(best_value) = result.best_value {
+            result.solution = self.tiebroken_solution(&problem, &relaxation, best_value).or(result.solution.take());
+        }
+
+        if let Some(path) = self.resume.as_ref() {
+            Self::save_incumbent(path, &reduction, &result);
+        }
+
+        if let (Some(path), Some(solution)) = (self.solution_out.as_ref(), result.solution.as_ref()) {
+            let mut selected = vec![false; problem.instance.nb_items + reduction.nb_removed()];
+            reduction.restore(solution).iter().for_each(|d| selected[d.variable.id()] = d.value == 1);
+            self.solution_format.write(path, &selected);
+        }
+
+        if self.auto_warm_start && !self.quiet && !self.json {
+            let greedy_value = problem.value_of(&problem.greedy().1);
+            println!("greedy value {greedy_value}");
+            println!("improved on greedy {}", result.best_value.is_some_and(|v| v > greedy_value));
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(path) = self.metrics_file.as_ref() {
+            std::fs::write(path, prometheus_metrics(&result)).unwrap();
+        }
+
+        let order = reduction.original_items(problem.order());
+
+        if self.json {
+            self.print_json(&reduction, &result, order, dropped);
+        } else {
+            if self.print_order && !self.quiet {
+                println!("order: {}", order.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(" "));
+            }
+            match result.best_value {
+                None if self.quiet => println!("none"),
+                None => {
+                    println!("is exact {}", result.is_exact);
+                    println!("no feasible solution");
+                },
+                Some(best_value) if self.quiet => println!("{}", self.display_value(best_value)),
+                Some(best_value) => self.report(&reduction, &problem, &relaxation, best_value, &result),
+            }
+        }
+
+        if self.fail_inexact && !result.is_exact {
+            std::process::exit(1);
+        }
+    }
+
+    fn print_json(&self, reduction: &Reduction, result: &SolveResult, order: Vec<usize>, dropped: Option<usize>) {
+        let restored = result.solution.as_ref().map(|s| reduction.restore(s));
 
-        let mut solver = ParBarrierSolverFc::new(&problem, &relaxation, &ranking, &width, &cutoff, &mut fringe);
+        let mut output = JsonOutput {
+            best_value: result.best_value,
+            is_exact: result.is_exact,
+            lower_bound: result.best_value,
+            upper_bound: (result.upper_bound != isize::MIN).then_some(result.upper_bound),
+            solution: None,
+            selected: None,
+            order: self.print_order.then_some(order),
+            dropped,
+            scaled_best_value: self.objective_scale.zip(result.best_value).map(|(s, v)| v as f64 / s),
+        };
+        if self.sparse_solution {
+            output.selected = restored.as_ref().map(|s| selected_items(s));
+        } else {
+            output.solution = restored.as_ref().map(|s| {
+                let mut bits = vec![0; s.len()];
+                s.iter().for_each(|d| bits[d.variable.id()] = d.value);
+                bits
+            });
+        }
 
-        let Completion{best_value, is_exact} = solver.maximize();
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+    }
 
-        let best_value = best_value.map(|v| v).unwrap_or(isize::MAX);
+    fn report(&self, reduction: &Reduction, problem: &Knapsack, relaxation: &KnapsackRelax, best_value: isize, result: &SolveResult) {
+        let is_exact = result.is_exact;
         println!("is exact {is_exact}");
-        println!("best value {best_value}");
+        if self.relaxation == RelaxationMode::None && !is_exact {
+            println!("only restricted DDs were compiled, the optimum is not proven");
+        }
+        println!("best value {}", self.display_value(best_value));
+        println!("bounds [{}, {}]", self.display_value(best_value), self.display_value(result.upper_bound));
+        println!("explored nodes {}", result.explored);
+
+        let solution = result.solution.as_ref().unwrap();
+        if self.tiebreak == TieBreak::MinWeight {
+            let weight = selected_items(solution).iter().map(|i| problem.instance.weight[*i]).sum::<isize>();
+            println!("min weight {}", problem.instance.unscale(weight));
+        }
+        println!("solution: {}", Self::format_solution(&reduction.restore(solution)));
+        self.print_fill(reduction, solution, best_value);
+
+        if self.all_optima {
+            if !is_exact {
+                println!("optimum not proven, enumerating the solutions matching the best value");
+            }
+
+            let Enumeration{solutions, truncated} = enumerate(problem, relaxation, best_value, self.max_optima);
+            println!("nb optima {}{}", solutions.len(), if truncated { " (truncated)" } else { "" });
+            for (_, optimum) in solutions.iter() {
+                println!("optimum: {}", Self::format_solution(&reduction.restore(optimum)));
+            }
+        }
+
+        if let (Some(gap), Some(solution)) = (self.near_optimal, result.solution.as_ref()) {
+            self.report_inclusion(reduction, problem, relaxation, best_value, solution, gap);
+        }
+    }
 
+    /// Among the solutions reaching the best value, the one chosen by --tiebreak
+    /// (which then replaces the solution found by the solver everywhere it is
+    /// reported), or None to keep the solution found by the solver
+    fn tiebroken_solution(&self, problem: &Knapsack, relaxation: &KnapsackRelax, best_value: isize) -> Option<Vec<Decision>> {
+        match self.tiebreak {
+            TieBreak::MinWeight => lightest_solution(problem, relaxation, best_value).map(|(_, solution)| solution),
+            TieBreak::Index => lexicographic_solution(problem, best_value),
+            TieBreak::Any => None,
+        }
+    }
+
+    /// Prints, for each item, whether the best solution selects it and the fraction
+    /// of the solutions whose value is within `gap` percent of the best value that
+    /// select it. At most --max-optima solutions are enumerated.
+    fn report_inclusion(&self, reduction: &Reduction, problem: &Knapsack, relaxation: &KnapsackRelax, best_value: isize, solution: &[Decision], gap: f64) {
+        let threshold = (best_value as f64 - gap / 100.0 * best_value.abs() as f64).ceil() as isize;
+        let Enumeration{solutions, truncated} = enumerate(problem, relaxation, threshold, self.max_optima);
+
+        let mut in_best = vec![0; reduction.instance.nb_items + reduction.nb_removed()];
+        reduction.restore(solution).iter().for_each(|d| in_best[d.variable.id()] = d.value);
+        let mut count = vec![0_usize; in_best.len()];
+        for (_, near) in solutions.iter() {
+            reduction.restore(near).iter()
+                .filter(|d| d.value == 1)
+                .for_each(|d| count[d.variable.id()] += 1);
+        }
+
+        println!("near optimal solutions {} (value >= {threshold}){}", solutions.len(), if truncated { " (truncated)" } else { "" });
+        println!("item,in_best,frequency");
+        for (item, (in_best, count)) in in_best.iter().zip(&count).enumerate() {
+            let frequency = *count as f64 / solutions.len().max(1) as f64;
+            println!("{item},{in_best},{frequency:.3}");
+        }
+    }
+
+    /// Reads the incumbent saved by a previous run with --resume, and maps it to the
+    /// items of the reduced instance. The incumbent is ignored when the file does not
+    /// exist, or when it does not describe a feasible solution of the reduced instance.
+    fn resumed_incumbent(&self, reduction: &Reduction) -> Option<Vec<usize>> {
+        let path = self.resume.as_ref()?;
+        let content = std::fs::read_to_string(path).ok()?;
+        let saved: SavedIncumbent = serde_json::from_str(&content)
+            .unwrap_or_else(|error| panic!("cannot parse the incumbent saved in {path}: {error}"));
+
+        let instance = &reduction.instance;
+        let selected = reduction.reduced_items(&saved.selected);
+        let feasible = selected.as_ref().is_some_and(|selected| {
+            selected.iter().map(|i| instance.weight[*i]).sum::<isize>() <= instance.capacity
+        });
+
+        if !feasible {
+            eprintln!("warning: the incumbent saved in {path} is not feasible for this instance, it is ignored");
+            return None;
+        }
+        if self.verbose && !self.json {
+            println!("resuming from an incumbent of value {} (previous upper bound {})", saved.best_value, saved.upper_bound);
+        }
+        selected
+    }
+
+    /// Saves the best solution found (with the original item indices) so that a
+    /// later run can resume from it
+    fn save_incumbent(path: &str, reduction: &Reduction, result: &SolveResult) {
+        if let (Some(best_value), Some(solution)) = (result.best_value, result.solution.as_ref()) {
+            let saved = SavedIncumbent {
+                best_value,
+                is_exact: result.is_exact,
+                upper_bound: result.upper_bound,
+                selected: selected_items(&reduction.restore(solution)),
+            };
+            std::fs::write(path, serde_json::to_string_pretty(&saved).unwrap()).unwrap();
+        }
+    }
+
+    /// Prints a one line summary of how the given solution of the reduced instance
+    /// fills the knapsack
+    fn print_fill(&self, reduction: &Reduction, solution: &[Decision], value: isize) {
+        let instance = &reduction.instance;
+        let selected = selected_items(solution);
+        let weight = selected.iter().map(|i| instance.weight[*i]).sum::<isize>();
+        let fill = if instance.capacity > 0 { 100.0 * weight as f64 / instance.capacity as f64 } else { 100.0 };
+        println!("selected {}/{} items, weight {}/{} ({fill:.1}% full), value {}",
+            selected.len(), instance.nb_items + reduction.nb_removed(),
+            instance.unscale(weight), instance.unscale(instance.capacity), self.display_value(value));
+    }
+
+    /// A value as reported to the user, divided by --objective-scale if any
+    fn display_value(&self, value: isize) -> String {
+        match self.objective_scale {
+            Some(scale) => (value as f64 / scale).to_string(),
+            None => value.to_string(),
+        }
+    }
+
+    fn load(&self) -> Result<KnapsackInstance, String> {
+        let mut instance = self.load_unpatched()?;
+        if let Some(path) = self.patch.as_ref() {
+            for patch in apply_patch(&mut instance, path)? {
+                if self.verbose && !self.json {
+                    println!("patched {patch}");
+                }
+            }
+        }
+        if let Some(capacity) = self.capacity_override.as_ref() {
+            instance.capacity = Self::resolve_capacity(capacity, &instance)?;
+            if !self.quiet && !self.json {
+                println!("capacity {}", instance.capacity);
+            }
+        }
+        Ok(instance)
+    }
+
+    /// Parses a capacity given either as an integer or as a percentage of the total
+    /// weight of the items (rounded down)
+    fn resolve_capacity(capacity: &str, instance: &KnapsackInstance) -> Result<isize, String> {
+        match capacity.trim().strip_suffix('%') {
+            Some(percent) => {
+                let percent: f64 = percent.trim().parse()
+                    .map_err(|_| format!("invalid capacity percentage: {capacity}"))?;
+                Ok((percent / 100.0 * instance.total_weight() as f64).floor() as isize)
+            },
+            None => capacity.trim().parse()
+                .map_err(|_| format!("invalid capacity: {capacity}, expected an integer or a percentage")),
+        }
+    }
+
+    fn load_unpatched(&self) -> Result<KnapsackInstance, String> {
+        if let Some(json) = self.instance_json.as_ref() {
+            parse_instance_json(json)
+        } else if let Some(items) = self.items_from.as_ref() {
+            compose_instance(items, self.given_capacity()?)
+        } else if let (Some(weights), Some(profits)) = (self.weights.as_ref(), self.profits.as_ref()) {
+            load_parallel_lists(weights, profits, self.given_capacity()?)
+        } else {
+            load_instance(self.instance.as_ref().unwrap(), self.format)
+        }
+    }
+
+    fn given_capacity(&self) -> Result<isize, String> {
+        match (self.capacity, self.capacity_from.as_ref()) {
+            (Some(capacity), _) => Ok(capacity),
+            (None, Some(path)) => load_capacity(path),
+            (None, None) => Err("either --capacity or --capacity-from is required when the instance is assembled from items".to_string()),
+        }
+    }
+
+    fn params(&self) -> SolverParams {
+        SolverParams {
+            width: self.width,
+            timeout: self.time_budget(),
+            dedup: !self.no_dedup,
+            max_nodes: self.max_nodes,
+            merge: self.relax_merge,
+            warm_start: self.auto_warm_start,
+            incumbent: None,
+            bound: !self.no_bound,
+            cancel: None,
+            restricted_only: self.relaxation == RelaxationMode::None,
+        }
+    }
+
+    fn time_budget(&self) -> Duration {
+        match self.timeout_ms {
+            Some(ms) => Duration::from_millis(ms),
+            None => Duration::from_secs(self.timeout),
+        }
+    }
+
+    fn format_solution(decisions: &[Decision]) -> String {
         let mut sol = String::new();
-        solver.best_solution().unwrap()
-            .iter().map(|d| d.value)
+        decisions.iter().map(|d| d.value)
             .for_each(|v| sol.push_str(&format!("{v} ")));
+        sol
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+    use std::time::Duration;
 
-        println!("solution: {sol}");
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::{SolverParams, selected_items, solve_knapsack};
+    use crate::instance::KnapsackInstance;
+    use crate::resolution::model::Knapsack;
+
+    /// A strongly correlated instance, which is far too large to be solved before
+    /// the resolution is cancelled
+    fn hard_instance() -> KnapsackInstance {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let weight = (0..2000).map(|_| rng.gen_range(1..=10_000)).collect::<Vec<isize>>();
+        let profit = weight.iter().map(|w| w + 1000).collect();
+        KnapsackInstance { nb_items: weight.len(), capacity: weight.iter().sum::<isize>() / 2, weight, profit, ..Default::default() }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn cancel_mid_solve() {
+        let problem = Knapsack::new(hard_instance());
+        let cancel = Arc::new(AtomicBool::new(false));
+        let params = SolverParams { timeout: Duration::from_secs(600), cancel: Some(cancel.clone()), ..Default::default() };
+
+        let result = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                while problem.nb_explored() == 0 {
+                    std::thread::yield_now();
+                }
+                cancel.store(true, Ordering::Relaxed);
+            });
+            solve_knapsack(&problem, &params)
+        });
+
+        assert!(cancel.load(Ordering::Relaxed));
+        assert!(!result.is_exact);
+        assert!(result.duration < params.timeout);
+    }
+
+    #[test]
+    fn oversized_items_keep_their_index() {
+        let instance = KnapsackInstance { nb_items: 3, capacity: 7, weight: vec![50, 3, 4], profit: vec![100, 3, 4], ..Default::default() };
+        let result = solve_knapsack(&Knapsack::new(instance), &SolverParams { incumbent: Some(vec![1]), ..Default::default() });
+
+        assert!(result.is_exact);
+        assert_eq!(result.best_value, Some(7));
+        let solution = result.solution.unwrap();
+        assert_eq!(solution.len(), 3);
+        assert_eq!(selected_items(&solution), vec![1, 2]);
+    }
+
+    #[test]
+    fn infeasible_warm_starts_are_ignored() {
+        // the greedy solution takes every item, from both clusters
+        let instance = KnapsackInstance { nb_items: 3, capacity: 3, weight: vec![1, 1, 1], profit: vec![10, 9, 2], cluster: Some(vec![0, 1, 1]), ..Default::default() };
+        let problem = Knapsack::new(instance.clone()).with_max_clusters(Some(1));
+        let result = solve_knapsack(&problem, &SolverParams { warm_start: true, ..Default::default() });
+        assert!(result.is_exact);
+        assert_eq!(result.best_value, Some(11));
+        assert_eq!(selected_items(&result.solution.unwrap()), vec![1, 2]);
+
+        let problem = Knapsack::new(KnapsackInstance { capacity: 2, ..instance });
+        let result = solve_knapsack(&problem, &SolverParams { incumbent: Some(vec![0, 1, 2]), ..Default::default() });
+        assert!(result.is_exact);
+        assert_eq!(result.best_value, Some(19));
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let instance = KnapsackInstance { nb_items: 2, capacity: 0, weight: vec![2, 3], profit: vec![5, 4], ..Default::default() };
+        let result = solve_knapsack(&Knapsack::new(instance), &SolverParams::default());
+        assert!(result.is_exact);
+        assert_eq!(result.best_value, Some(0));
+        assert!(selected_items(&result.solution.unwrap()).is_empty());
+
+        let instance = KnapsackInstance { nb_items: 2, capacity: 0, weight: vec![0, 3], profit: vec![5, 4], ..Default::default() };
+        let result = solve_knapsack(&Knapsack::new(instance), &SolverParams::default());
+        assert_eq!(result.best_value, Some(5));
+        assert_eq!(selected_items(&result.solution.unwrap()), vec![0]);
+    }
+
+    #[test]
+    fn negative_capacity() {
+        let instance = KnapsackInstance { nb_items: 2, capacity: -1, weight: vec![0, 3], profit: vec![5, 4], ..Default::default() };
+        let result = solve_knapsack(&Knapsack::new(instance), &SolverParams::default());
+        assert!(result.is_exact);
+        assert_eq!(result.best_value, None);
+        assert!(result.solution.is_none());
+    }
+}
@@ -0,0 +1,62 @@
+//! This module implements the `scenarios` subcommand, which solves an instance
+//! once for each of its profit scenarios. The weights and the capacity are shared
+//! by all the scenarios, only the profits change.
+
+use std::time::Duration;
+
+use clap::Args;
+
+use crate::format::Format;
+use crate::instance::KnapsackInstance;
+use crate::load::load_instance;
+use crate::resolution::{Knapsack, SolverParams, solve_knapsack};
+
+#[derive(Debug, Args)]
+pub struct Scenarios {
+    /// The path to the instance file
+    #[clap(short, long)]
+    pub instance: String,
+    /// The format of the instance file (guessed from its content when omitted)
+    #[clap(short, long, value_enum)]
+    pub format: Option<Format>,
+    /// max number of nodes in a layer
+    #[clap(short, long, default_value="100")]
+    pub width: usize,
+    /// timeout (in seconds) of the resolution of each scenario
+    #[clap(short, long, default_value="60")]
+    pub timeout: u64,
+}
+
+impl Scenarios {
+    pub fn scenarios(&self) {
+        let instance = load_instance(&self.instance, self.format).unwrap_or_else(|error| panic!("{error}"));
+        let scenarios = instance.profit_scenarios.clone()
+            .unwrap_or_else(|| panic!("the instance {} has no profit scenarios", self.instance));
+
+        let params = SolverParams {
+            width: self.width,
+            timeout: Duration::from_secs(self.timeout),
+            ..Default::default()
+        };
+
+        // the number of scenarios in which each item is selected
+        let nb_scenarios = scenarios.len();
+        let mut selections = vec![0; instance.nb_items];
+        for (s, profit) in scenarios.into_iter().enumerate() {
+            let scenario = KnapsackInstance { profit, profit_scenarios: None, ..instance.clone() };
+            let result = solve_knapsack(&Knapsack::new(scenario), &params);
+            println!("scenario {s}: {result}");
+
+            for decision in result.solution.unwrap_or_default().iter().filter(|d| d.value == 1) {
+                selections[decision.variable.id()] += 1;
+            }
+        }
+
+        // an item is robust when it is selected in the solution of every scenario
+        let robust = (0..instance.nb_items)
+            .filter(|i| nb_scenarios > 0 && selections[*i] == nb_scenarios)
+            .map(|i| i.to_string())
+            .collect::<Vec<String>>();
+        println!("robust items {}", robust.join(","));
+    }
+}
@@ -0,0 +1,107 @@
+//! This module implements the files in which the solutions of an instance can be
+//! stored, and the `verify` subcommand that checks them.
+//!
+//! * `json`: an array holding a 0 or a 1 for each item of the instance.
+//! * `packed`: a binary file made of the number of items as an unsigned 64 bits
+//!   little endian integer, followed by one bit per item, in the order of the
+//!   original item indices. Item `i` is stored in byte `i / 8` (after the header),
+//!   at bit `i % 8` counted from the least significant bit. The unused bits of the
+//!   last byte are zero.
+
+use clap::{Args, ValueEnum};
+
+use crate::format::Format;
+use crate::load::load_instance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SolutionFormat {
+    Json,
+    Packed,
+}
+
+impl SolutionFormat {
+    /// Writes the solution, given as whether each item is selected, to the given file
+    pub fn write(&self, path: &str, selected: &[bool]) {
+        let content = match self {
+            SolutionFormat::Json => {
+                let bits = selected.iter().map(|s| *s as u8).collect::<Vec<u8>>();
+                serde_json::to_vec(&bits).unwrap()
+            },
+            SolutionFormat::Packed => {
+                let mut content = (selected.len() as u64).to_le_bytes().to_vec();
+                let mut bytes = vec![0_u8; selected.len().div_ceil(8)];
+                for (i, _) in selected.iter().enumerate().filter(|(_, s)| **s) {
+                    bytes[i / 8] |= 1 << (i % 8);
+                }
+                content.extend(bytes);
+                content
+            },
+        };
+        std::fs::write(path, content).unwrap();
+    }
+
+    /// Reads a solution written in this format
+    pub fn read(&self, path: &str) -> Result<Vec<bool>, String> {
+        let content = std::fs::read(path).map_err(|e| e.to_string())?;
+        match self {
+            SolutionFormat::Json => {
+                let bits: Vec<u8> = serde_json::from_slice(&content).map_err(|e| e.to_string())?;
+                bits.iter().map(|b| match b {
+                    0 => Ok(false),
+                    1 => Ok(true),
+                    _ => Err(format!("invalid decision {b}")),
+                }).collect()
+            },
+            SolutionFormat::Packed => {
+                let header = content.get(..8).ok_or("missing header")?;
+                let nb_items = u64::from_le_bytes(header.try_into().unwrap()) as usize;
+                let bytes = &content[8..];
+                if bytes.len() != nb_items.div_ceil(8) {
+                    return Err(format!("expected {} bytes for {nb_items} items, found {}", nb_items.div_ceil(8), bytes.len()));
+                }
+                Ok((0..nb_items).map(|i| bytes[i / 8] & (1 << (i % 8)) != 0).collect())
+            },
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct Verify {
+    /// The path to the instance file
+    #[clap(short, long)]
+    pub instance: String,
+    /// The format of the instance file (guessed from its content when omitted)
+    #[clap(short, long, value_enum)]
+    pub format: Option<Format>,
+    /// The path to the solution file
+    #[clap(short, long)]
+    pub solution: String,
+    /// The format of the solution file
+    #[clap(long, value_enum, default_value="json")]
+    pub solution_format: SolutionFormat,
+}
+
+impl Verify {
+    pub fn verify(&self) {
+        let instance = load_instance(&self.instance, self.format).unwrap_or_else(|error| panic!("{error}"));
+        let selected = self.solution_format.read(&self.solution)
+            .unwrap_or_else(|error| panic!("cannot read the solution {}: {error}", self.solution));
+
+        if selected.len() != instance.nb_items {
+            eprintln!("invalid solution: {} decisions for {} items", selected.len(), instance.nb_items);
+            std::process::exit(1);
+        }
+
+        let items = (0..instance.nb_items).filter(|i| selected[*i]);
+        let weight = items.clone().map(|i| instance.weight[i]).sum::<isize>();
+        let value = items.map(|i| instance.profit[i]).sum::<isize>();
+
+        println!("value {value}");
+        println!("weight {}/{}", instance.unscale(weight), instance.unscale(instance.capacity));
+        if weight > instance.capacity {
+            println!("infeasible");
+            std::process::exit(1);
+        }
+        println!("feasible");
+    }
+}
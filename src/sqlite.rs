@@ -0,0 +1,49 @@
+//! This module stores the results of the bench command in a SQLite database
+//! (requires the `sqlite` feature), so that they can be queried with SQL.
+
+use rusqlite::{Connection, params};
+
+use crate::resolution::SolveResult;
+
+/// A database holding one row per solved instance
+#[derive(Debug)]
+pub struct ResultsDb {
+    conn: Connection,
+}
+
+impl ResultsDb {
+    /// Opens the database at the given path, creating it and its `results` table
+    /// when they do not exist yet
+    pub fn open(path: &str) -> Self {
+        let conn = Connection::open(path).unwrap();
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS results (
+                instance TEXT NOT NULL,
+                value    INTEGER,
+                exact    INTEGER NOT NULL,
+                nodes    INTEGER NOT NULL,
+                time_ms  INTEGER NOT NULL,
+                width    INTEGER NOT NULL,
+                solver   TEXT NOT NULL
+            )",
+            [],
+        ).unwrap();
+        ResultsDb { conn }
+    }
+
+    /// Appends the result of the given instance
+    pub fn insert(&self, instance: &str, result: &SolveResult, width: usize) {
+        self.conn.execute(
+            "INSERT INTO results (instance, value, exact, nodes, time_ms, width, solver) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                instance,
+                result.best_value.map(|v| v as i64),
+                result.is_exact,
+                result.explored as i64,
+                result.duration.as_millis() as i64,
+                width as i64,
+                "ParBarrierSolverFc",
+            ],
+        ).unwrap();
+    }
+}
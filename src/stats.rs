@@ -0,0 +1,118 @@
+use clap::Args;
+
+use crate::difficulty::correlation;
+use crate::format::Format;
+use crate::instance::KnapsackInstance;
+use crate::load::load_instance;
+use crate::resolution::Knapsack;
+
+/// The width (in characters) of the longest bar of a histogram
+const BAR_WIDTH: usize = 50;
+
+#[derive(Debug, Args)]
+pub struct Stats {
+    /// The path to the instance file
+    #[clap(short, long)]
+    pub instance: String,
+    /// The format of the instance file (guessed from its content when omitted)
+    #[clap(short, long, value_enum)]
+    pub format: Option<Format>,
+    /// Print histograms of the weights and profits of the items
+    #[clap(long)]
+    pub histogram: bool,
+    /// Report how many items share the profit/weight ratio of another item
+    #[clap(long)]
+    pub report_order_stability: bool,
+    /// Print the Pearson correlation coefficient between the weights and the profits
+    #[clap(long)]
+    pub weight_profit_correlation: bool,
+    /// Report the groups of items with identical weight and profit
+    #[clap(long)]
+    pub report_duplicates: bool,
+    /// The number of buckets of the histograms
+    #[clap(long, default_value="10", value_parser = clap::value_parser!(u64).range(1..))]
+    pub bins: u64,
+}
+
+impl Stats {
+    pub fn stats(&self) {
+        let instance = load_instance(&self.instance, self.format).unwrap_or_else(|error| panic!("{error}"));
+
+        println!("items {}", instance.nb_items);
+        if let Some(scale) = instance.scale {
+            println!("scale {scale}");
+        }
+        // the weights are displayed in their original unit when the instance is scaled
+        println!("capacity {}", instance.unscale(instance.capacity));
+        Self::summary("weight", &instance.weight, |w| instance.unscale(w));
+        Self::summary("profit", &instance.profit, |p| p as f64);
+        if let Some(cluster) = instance.cluster.as_ref() {
+            println!("clusters {}", cluster.iter().max().map(|c| c + 1).unwrap_or(0));
+        }
+
+        if self.weight_profit_correlation {
+            println!("weight/profit correlation {:.4}", correlation(&instance.weight, &instance.profit));
+        }
+
+        if self.report_duplicates {
+            let (groups, largest) = instance.duplicate_groups();
+            println!("duplicate groups {groups}, largest group {largest}");
+        }
+
+        if self.report_order_stability {
+            Self::order_stability(&instance);
+        }
+
+        if self.histogram {
+            println!();
+            self.histogram_of("weight", &instance.weight);
+            println!();
+            self.histogram_of("profit", &instance.profit);
+        }
+    }
+
+    fn summary(name: &str, values: &[isize], unit: impl Fn(isize) -> f64) {
+        let total = values.iter().sum::<isize>();
+        let mean = unit(total) / values.len().max(1) as f64;
+        let min = unit(values.iter().min().copied().unwrap_or(0));
+        let max = unit(values.iter().max().copied().unwrap_or(0));
+        let total = unit(total);
+        println!("{name} total {total} min {min} max {max} mean {mean:.2}");
+    }
+
+    /// Counts the items whose ratio ties with another item in the order used by the
+    /// solver. The tied items are sorted by index, so the optimum reported among
+    /// several equivalent ones may depend on the numbering of the items.
+    fn order_stability(instance: &KnapsackInstance) {
+        let (tied_items, tie_groups) = Knapsack::new(instance.clone()).ratio_ties();
+        println!("tied items {tied_items} in {tie_groups} groups of equal ratio");
+    }
+
+    /// Prints an ASCII histogram of the given values, bucketed into equally
+    /// wide intervals between their minimum and maximum.
+    fn histogram_of(&self, name: &str, values: &[isize]) {
+        println!("{name}");
+        let (Some(&min), Some(&max)) = (values.iter().min(), values.iter().max()) else {
+            return;
+        };
+
+        let bins = self.bins as usize;
+        let span = (max - min) as usize + 1;
+        let bucket = span.div_ceil(bins);
+        let mut counts = vec![0_usize; bins];
+        for v in values {
+            counts[(*v - min) as usize / bucket] += 1;
+        }
+
+        let highest = counts.iter().copied().max().unwrap_or(0).max(1);
+        for (i, count) in counts.iter().enumerate() {
+            let low = min + (i * bucket) as isize;
+            if low > max {
+                break;
+            }
+            let high = (low + bucket as isize - 1).min(max);
+            let bar = "#".repeat(count * BAR_WIDTH / highest);
+            println!("{low:>10} - {high:<10} {count:>6} {bar}");
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! End to end golden tests: instances generated with a fixed seed are solved with
+//! decision diagrams, and the result must match the optimal value and the items
+//! recorded below. These expectations are committed on purpose, so that a change
+//! of the generation (or of its random stream) fails these tests just like a
+//! change of the solver does. The dp solver is only used as an extra cross-check.
+
+use clap::Parser;
+
+use knapsack::generate::KnapsackGenerator;
+use knapsack::resolution::{Knapsack, SolverParams, selected_items, solve_dp, solve_knapsack};
+
+#[derive(Debug, Parser)]
+struct Cli {
+    #[command(flatten)]
+    generator: KnapsackGenerator,
+}
+
+/// Generates 30 items (10 per size class) with the given seed and capacity, solves
+/// the instance and checks the result against the expected optimum and items
+fn check_golden(seed: u128, capacity: isize, optimum: isize, items: &[usize]) {
+    let seed = seed.to_string();
+    let capacity = capacity.to_string();
+    let args = ["generate", "--seed", seed.as_str(), "--small", "10", "--medium", "10", "--large", "10", "--capacity", capacity.as_str()];
+    let instance = Cli::parse_from(args).generator.sample();
+    assert_eq!(instance.nb_items, 30);
+
+    let result = solve_knapsack(&Knapsack::new(instance.clone()), &SolverParams::default());
+    assert!(result.is_exact);
+    assert_eq!(result.best_value, Some(optimum));
+    assert_eq!(selected_items(&result.solution.unwrap()), items);
+
+    let (dp_optimum, dp_items) = solve_dp(&instance);
+    assert_eq!(dp_optimum, optimum);
+    assert_eq!(dp_items, items);
+}
+
+#[test]
+fn golden_tight_capacity() {
+    // about 6 of the 30 items fit
+    check_golden(42, 20_000, 26850, &[0, 3, 5, 9, 15, 25]);
+    check_golden(7, 20_000, 28002, &[1, 3, 4, 7, 9, 15, 18]);
+}
+
+#[test]
+fn golden_loose_capacity() {
+    // about 21 of the 30 items fit
+    check_golden(42, 120_000, 131462, &[0, 2, 3, 5, 7, 9, 13, 15, 16, 17, 18, 19, 20, 22, 23, 24, 25, 26, 27, 28, 29]);
+    check_golden(7, 120_000, 124217, &[1, 3, 4, 5, 7, 9, 10, 12, 13, 14, 15, 18, 19, 20, 22, 24, 25, 26, 27, 28, 29]);
+}